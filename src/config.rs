@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::Deserialize;
+use crate::error::{Error, Result};
+use crate::utils::glob::glob_match;
+use crate::verilog::diagnostic::{DiagnosticCollector, DiagnosticKind, Severity};
+
+pub const CONFIG_FILE_NAME: &str = "generate_top.toml";
+
+///
+/// per-project configuration, loaded from `generate_top.toml`
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// include directories of verilog source files
+    pub include_dirs: Vec<PathBuf>,
+    /// preprocessor defines, `NAME` or `NAME=VALUE`
+    pub defines: Vec<String>,
+    /// headers whose `define macros are visible to all the verilog files
+    pub headers: Vec<PathBuf>,
+    /// verilog files which can not be parsed are skipped with warning
+    pub skip_parse_errors: bool,
+    /// verilog source files which are not parsed, glob pattern is supported
+    pub exclude: Vec<String>,
+    /// modules instantiated in top, all the modules when empty, glob pattern is supported
+    pub include_modules: Vec<String>,
+    /// modules not instantiated in top, they are still used as library definitions
+    pub exclude_modules: Vec<String>,
+    /// library directories, `<module>.v` & `<module>.sv` in them define the modules of excel sheets
+    pub lib_dirs: Vec<PathBuf>,
+    /// library files searched for module definitions, the modules in them are not instantiated
+    pub lib_files: Vec<PathBuf>,
+    /// directory of the cache of parsed module interfaces, no cache when it is not set
+    pub cache_dir: Option<PathBuf>,
+    pub naming: NamingConfig,
+    pub output: OutputConfig,
+    /// health check severity of each finding kind, such as `unload = "error"`
+    pub severity: HashMap<String, Severity>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NamingConfig {
+    /// prefix of default instance name
+    pub inst_prefix: String,
+}
+
+impl Default for NamingConfig {
+    fn default() -> Self {
+        Self { inst_prefix: "u_".into() }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    pub dialect: Dialect,
+    pub excel: Option<PathBuf>,
+    pub out: Option<PathBuf>,
+    pub module_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    #[default]
+    Verilog,
+    #[value(name = "systemverilog")]
+    SystemVerilog,
+}
+
+impl Dialect {
+    ///
+    /// keyword used to declare ports & wires
+    ///
+    pub fn net_type(&self) -> &'static str {
+        match self {
+            Dialect::Verilog => "wire",
+            Dialect::SystemVerilog => "logic",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Dialect::Verilog => "v",
+            Dialect::SystemVerilog => "sv",
+        }
+    }
+}
+
+impl Config {
+    ///
+    /// walk up from `start` to find `generate_top.toml`
+    ///
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        let start = start.canonicalize().unwrap_or(start.to_path_buf());
+        start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|p| p.is_file())
+    }
+
+    ///
+    /// load config file, relative paths are based on the config file directory
+    ///
+    pub fn load(path: &Path) -> Result<Config> {
+        let text = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let mut config: Config = toml::from_str(&text)
+            .map_err(|e| Error::Config { file: path.to_path_buf(), msg: e.to_string() })?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        config.include_dirs = config.include_dirs.iter().map(|p| base.join(p)).collect();
+        config.headers = config.headers.iter().map(|p| base.join(p)).collect();
+        config.lib_dirs = config.lib_dirs.iter().map(|p| base.join(p)).collect();
+        config.lib_files = config.lib_files.iter().map(|p| base.join(p)).collect();
+        config.cache_dir = config.cache_dir.map(|p| base.join(p));
+        config.output.excel = config.output.excel.map(|p| base.join(p));
+        config.output.out = config.output.out.map(|p| base.join(p));
+        log::info!("load config file {}", path.display());
+        Ok(config)
+    }
+
+    ///
+    /// load the config file found from `start`, or the default config
+    ///
+    pub fn load_from(start: &Path) -> Result<Config> {
+        match Self::discover(start) {
+            Some(p) => Self::load(&p),
+            None => Ok(Config::default()),
+        }
+    }
+
+    ///
+    /// split defines into name & value
+    ///
+    pub fn define_pairs(&self) -> Vec<(String, Option<String>)> {
+        self.defines
+            .iter()
+            .map(|d| match d.split_once('=') {
+                Some((name, value)) => (name.trim().to_string(), Some(value.trim().to_string())),
+                None => (d.trim().to_string(), None),
+            })
+            .collect()
+    }
+
+    ///
+    /// check the file should be excluded
+    ///
+    pub fn is_excluded(&self, file: &Path) -> bool {
+        let full = file.to_string_lossy();
+        let name = file.file_name().map(|s| s.to_string_lossy()).unwrap_or_default();
+        self.exclude.iter().any(|pattern| {
+            glob_match(pattern, &name) || glob_match(pattern, &full)
+        })
+    }
+
+    ///
+    /// check the module should be instantiated in top
+    ///
+    pub fn is_module_selected(&self, name: &str) -> bool {
+        let included = self.include_modules.is_empty()
+            || self.include_modules.iter().any(|pattern| glob_match(pattern, name));
+        included && !self.exclude_modules.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    ///
+    /// register severity of health check findings
+    ///
+    pub fn apply_severity(&self) {
+        for (kind, severity) in self.severity.iter() {
+            match DiagnosticKind::from_str(kind) {
+                Ok(k) => DiagnosticCollector::set_severity(k, *severity),
+                Err(_) => log::warn!("unknown health check kind `{}` in config", kind),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config: Config = toml::from_str(r#"
+            include_dirs = ["inc"]
+            defines = ["SIM", "WIDTH=8"]
+            exclude = ["*_tb.v"]
+            include_modules = ["uart_*", "spi_*"]
+            exclude_modules = ["*_model"]
+
+            [naming]
+            inst_prefix = "i_"
+
+            [output]
+            dialect = "systemverilog"
+
+            [severity]
+            unload = "error"
+        "#).unwrap();
+        assert_eq!(config.define_pairs(), vec![("SIM".to_string(), None), ("WIDTH".to_string(), Some("8".to_string()))]);
+        assert_eq!(config.output.dialect, Dialect::SystemVerilog);
+        assert_eq!(config.naming.inst_prefix, "i_");
+        assert!(config.is_excluded(Path::new("rtl/uart_tb.v")));
+        assert!(config.is_module_selected("uart_rx"));
+        assert!(!config.is_module_selected("uart_model"));
+        assert!(!config.is_module_selected("i2c_master"));
+        assert_eq!(config.severity.get("unload"), Some(&Severity::Error));
+    }
+}
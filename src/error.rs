@@ -0,0 +1,176 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+///
+/// position of a cell in excel, used to report error
+///
+#[derive(Debug, Clone)]
+pub struct CellPos {
+    pub file: PathBuf,
+    pub sheet: String,
+    pub row: u32,
+    pub col: u32,
+}
+
+impl CellPos {
+    pub fn new(file: &Path, sheet: &str, row: u32, col: u32) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            sheet: sheet.into(),
+            row,
+            col,
+        }
+    }
+
+    ///
+    /// column name in excel, such as `A`, `B`, `AA`
+    ///
+    fn col_name(&self) -> String {
+        let mut res = Vec::new();
+        let mut n = self.col + 1;
+        while n > 0 {
+            let rem = (n - 1) % 26;
+            res.push((b'A' + rem as u8) as char);
+            n = (n - 1) / 26;
+        }
+        res.iter().rev().collect()
+    }
+}
+
+impl Display for CellPos {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} sheet `{}` cell {}{} (row {}, column {})",
+            self.file.display(),
+            self.sheet,
+            self.col_name(),
+            self.row + 1,
+            self.row + 1,
+            self.col + 1
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{pos}: illegal name `{name}`")]
+    IllegalName { pos: CellPos, name: String },
+
+    #[error("{pos}: illegal width `{value}`")]
+    IllegalWidth { pos: CellPos, value: String },
+
+    #[error("{pos}: illegal wire `{value}`")]
+    IllegalWire { pos: CellPos, value: String },
+
+    #[error("excel {0} already exists")]
+    ExcelExists(PathBuf),
+
+    #[error("excel {0} is empty")]
+    EmptyWorkbook(PathBuf),
+
+    #[error("can not read excel {file}: {source}")]
+    ReadExcel {
+        file: PathBuf,
+        #[source]
+        source: calamine::Error,
+    },
+
+    #[error("can not write excel {file}: {source}")]
+    WriteExcel {
+        file: PathBuf,
+        #[source]
+        source: rust_xlsxwriter::XlsxError,
+    },
+
+    #[error("can not write sheet `{sheet}` of excel {file}: {source}")]
+    Sheet {
+        file: PathBuf,
+        sheet: String,
+        #[source]
+        source: rust_xlsxwriter::XlsxError,
+    },
+
+    #[error("file {file} parse error: {msg}")]
+    Parse { file: PathBuf, msg: String },
+
+    #[error("{file}:{line}:{col}: syntax error\n{snippet}")]
+    Syntax {
+        file: PathBuf,
+        line: usize,
+        col: usize,
+        snippet: String,
+    },
+
+    #[error("can not import {file}: {msg}")]
+    Import { file: PathBuf, msg: String },
+
+    #[error("config {file}: {msg}")]
+    Config { file: PathBuf, msg: String },
+
+    #[error("filelist {file}: {msg}")]
+    FileList { file: PathBuf, msg: String },
+
+    #[error("{file}: {source}")]
+    Io {
+        file: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("array `{0}` has no driver, connect it or generate the top in systemverilog to make it a port")]
+    UndrivenArray(String),
+
+    #[error("invalid path {0}")]
+    InvalidPath(PathBuf),
+}
+
+impl Error {
+    pub fn io(file: &Path, source: std::io::Error) -> Self {
+        Error::Io {
+            file: file.to_path_buf(),
+            source,
+        }
+    }
+
+    ///
+    /// syntax error at byte `offset` of `text`, line & column start from 1
+    /// the snippet is the source line with a caret under the column
+    ///
+    pub fn syntax(file: &Path, text: &str, offset: usize) -> Self {
+        let offset = offset.min(text.len());
+        let start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+        let line = text[..offset].matches('\n').count() + 1;
+        let col = text[start..offset].chars().count() + 1;
+        let source = text[start..end].trim_end_matches('\r');
+        let prefix = format!("{:>5} | ", line);
+        let snippet = format!("{}{}\n{}^", prefix, source, " ".repeat(prefix.len() + col - 1));
+        Error::Syntax { file: file.to_path_buf(), line, col, snippet }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cell_pos() {
+        let pos = CellPos::new(&PathBuf::from("top.xlsx"), "uart_tx", 4, 27);
+        assert_eq!(pos.col_name(), "AB");
+        let err = Error::IllegalName { pos, name: "1abc".into() };
+        assert_eq!(
+            err.to_string(),
+            "top.xlsx sheet `uart_tx` cell AB5 (row 5, column 28): illegal name `1abc`"
+        );
+    }
+
+    #[test]
+    fn test_syntax() {
+        let text = "module top (\n    input a b\n);\nendmodule\n";
+        let err = Error::syntax(Path::new("top.v"), text, text.find('b').unwrap());
+        assert_eq!(err.to_string(), "top.v:2:13: syntax error\n    2 |     input a b\n                    ^");
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::{Display, Formatter};
+use colored::Colorize;
+use crate::verilog::parameter::Param;
+use crate::verilog::port::VerilogPort;
+
+///
+/// changes made by `ExcelWriter::update` when rtl is synced into excel
+///
+#[derive(Debug, Default)]
+pub struct ChangeReport {
+    pub added_ports: Vec<PortChange>,
+    pub dropped_ports: Vec<PortChange>,
+    pub width_changes: Vec<WidthChange>,
+    pub new_insts: Vec<String>,
+    pub deleted_insts: Vec<String>,
+    pub param_changes: Vec<ParamChange>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortChange {
+    pub sheet: String,
+    pub port: String,
+    pub width: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WidthChange {
+    pub sheet: String,
+    pub port: String,
+    pub old: usize,
+    pub new: usize,
+}
+
+///
+/// `old` is `None` if the parameter is added in rtl
+/// `new` is `None` if the parameter is removed from rtl
+/// both are set if the value in excel differs from rtl
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamChange {
+    pub sheet: String,
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl ChangeReport {
+    pub fn is_empty(&self) -> bool {
+        self.added_ports.is_empty()
+            && self.dropped_ports.is_empty()
+            && self.width_changes.is_empty()
+            && self.new_insts.is_empty()
+            && self.deleted_insts.is_empty()
+            && self.param_changes.is_empty()
+    }
+
+    ///
+    /// compare ports of a sheet, `old` is from excel & `new` is from rtl
+    /// ports in both sides with different width are width changes
+    ///
+    pub fn add_port_diff(&mut self, sheet: &str, old: &[&VerilogPort], new: &[&VerilogPort]) {
+        for p in new.iter() {
+            match old.iter().find(|o| o.name == p.name) {
+                Some(o) if o.width.width() != p.width.width() => self.width_changes.push(WidthChange {
+                    sheet: sheet.into(),
+                    port: p.name.clone(),
+                    old: o.width.width(),
+                    new: p.width.width(),
+                }),
+                Some(_) => {}
+                None => self.added_ports.push(PortChange {
+                    sheet: sheet.into(),
+                    port: p.name.clone(),
+                    width: p.width.width(),
+                }),
+            }
+        }
+        for o in old.iter().filter(|o| !new.iter().any(|p| p.name == o.name)) {
+            self.dropped_ports.push(PortChange {
+                sheet: sheet.into(),
+                port: o.name.clone(),
+                width: o.width.width(),
+            });
+        }
+    }
+
+    ///
+    /// compare parameters of a sheet, `old` is from excel & `new` is from rtl
+    /// localparam is not in excel
+    ///
+    pub fn add_param_diff(&mut self, sheet: &str, old: &[Param], new: &[Param]) {
+        for p in new.iter().filter(|p| !p.local) {
+            let old = match old.iter().find(|o| o.name == p.name) {
+                Some(o) if o.value_string() == p.value_string() => continue,
+                Some(o) => Some(o.value_string()),
+                None => None,
+            };
+            self.param_changes.push(ParamChange {
+                sheet: sheet.into(),
+                name: p.name.clone(),
+                old,
+                new: Some(p.value_string()),
+            });
+        }
+        for o in old.iter().filter(|o| !new.iter().any(|p| p.name == o.name)) {
+            self.param_changes.push(ParamChange {
+                sheet: sheet.into(),
+                name: o.name.clone(),
+                old: Some(o.value_string()),
+                new: None,
+            });
+        }
+    }
+}
+
+impl Display for ChangeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "excel is up to date");
+        }
+        if !self.new_insts.is_empty() {
+            writeln!(f, "{}", "New instances:".bold())?;
+            for s in self.new_insts.iter() {
+                writeln!(f, "  {} {}", "+".green(), s)?;
+            }
+        }
+        if !self.deleted_insts.is_empty() {
+            writeln!(f, "{}", "Deleted instances:".bold())?;
+            for s in self.deleted_insts.iter() {
+                writeln!(f, "  {} {}", "-".red(), s)?;
+            }
+        }
+        if !self.added_ports.is_empty() {
+            writeln!(f, "{}", "Added ports:".bold())?;
+            for p in self.added_ports.iter() {
+                writeln!(f, "  {} {}.{} [{}]", "+".green(), p.sheet, p.port, p.width)?;
+            }
+        }
+        if !self.dropped_ports.is_empty() {
+            writeln!(f, "{}", "Dropped ports:".bold())?;
+            for p in self.dropped_ports.iter() {
+                writeln!(f, "  {} {}.{} [{}]", "-".red(), p.sheet, p.port, p.width)?;
+            }
+        }
+        if !self.width_changes.is_empty() {
+            writeln!(f, "{}", "Width changes:".bold())?;
+            for p in self.width_changes.iter() {
+                writeln!(f, "  {} {}.{} {} -> {}", "~".yellow(), p.sheet, p.port, p.old, p.new)?;
+            }
+        }
+        if !self.param_changes.is_empty() {
+            writeln!(f, "{}", "Parameter changes:".bold())?;
+            for p in self.param_changes.iter() {
+                match (&p.old, &p.new) {
+                    (None, Some(n)) => writeln!(f, "  {} {}.{} = {}", "+".green(), p.sheet, p.name, n)?,
+                    (Some(o), None) => writeln!(f, "  {} {}.{} = {}", "-".red(), p.sheet, p.name, o)?,
+                    (Some(o), Some(n)) => writeln!(f, "  {} {}.{} {} -> {}", "~".yellow(), p.sheet, p.name, o, n)?,
+                    (None, None) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::verilog::port::PortDir;
+
+    #[test]
+    fn test_port_diff() {
+        let a = VerilogPort::new(PortDir::InPort, "a", 8.into());
+        let b_old = VerilogPort::new(PortDir::InPort, "b", 4.into());
+        let b_new = VerilogPort::new(PortDir::InPort, "b", 8.into());
+        let c = VerilogPort::new(PortDir::OutPort, "c", 1.into());
+        let mut report = ChangeReport::default();
+        report.add_port_diff("uart_tx", &[&a, &b_old], &[&b_new, &c]);
+        assert_eq!(report.added_ports, vec![PortChange { sheet: "uart_tx".into(), port: "c".into(), width: 1 }]);
+        assert_eq!(report.dropped_ports, vec![PortChange { sheet: "uart_tx".into(), port: "a".into(), width: 8 }]);
+        assert_eq!(report.width_changes, vec![WidthChange { sheet: "uart_tx".into(), port: "b".into(), old: 4, new: 8 }]);
+    }
+
+    #[test]
+    fn test_param_diff() {
+        let old = vec![
+            Param::new("DEPTH".into(), 16),
+            Param::new("AW".into(), 4).with_expr("$clog2(DEPTH)"),
+            Param::new("MODE".into(), 0),
+        ];
+        let new = vec![
+            Param::new("DEPTH".into(), 16),
+            Param::new("AW".into(), 5).with_expr("$clog2(DEPTH) + 1"),
+            Param::new("ID".into(), 1),
+            Param::new("LAST".into(), 15).with_local(true),
+        ];
+        let mut report = ChangeReport::default();
+        report.add_param_diff("fifo", &old, &new);
+        let change = |name: &str, old: Option<&str>, new: Option<&str>| ParamChange {
+            sheet: "fifo".into(),
+            name: name.into(),
+            old: old.map(String::from),
+            new: new.map(String::from),
+        };
+        assert_eq!(report.param_changes, vec![
+            change("AW", Some("$clog2(DEPTH)"), Some("$clog2(DEPTH) + 1")),
+            change("ID", None, Some("1")),
+            change("MODE", Some("0"), None),
+        ]);
+        assert!(report.to_string().contains("fifo.AW $clog2(DEPTH) -> $clog2(DEPTH) + 1"));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::excel::writer::ExcelWriter;
+use crate::verilog::data::WrapMacro;
+use crate::verilog::module::VerilogModule;
+use crate::verilog::parse::VerilogParser;
+use crate::verilog::port::{PortDir, VerilogPort, VerilogValue};
+use crate::verilog::width::Width;
+
+///
+/// convert a hand-written verilog top into excel
+/// definitions of instantiated modules are searched in `library`
+///
+pub struct TopImporter {
+    file: PathBuf,
+    module_name: Option<String>,
+    library: Vec<VerilogModule>,
+    packages: HashMap<String, usize>,
+    config: Config,
+}
+
+impl TopImporter {
+    pub fn new(file: PathBuf) -> Self {
+        Self {
+            file,
+            module_name: None,
+            library: Vec::new(),
+            packages: HashMap::new(),
+            config: Config::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.config = config.clone();
+        self
+    }
+
+    ///
+    /// module to import, default is the module which is not instantiated in the file
+    ///
+    pub fn with_module_name(mut self, name: Option<String>) -> Self {
+        self.module_name = name;
+        self
+    }
+
+    ///
+    /// modules used to resolve direction & width of instance ports
+    ///
+    pub fn with_library(mut self, modules: Vec<VerilogModule>) -> Self {
+        self.library = modules;
+        self
+    }
+
+    ///
+    /// types of packages used by ports of the top
+    ///
+    pub fn with_packages(mut self, types: HashMap<String, usize>) -> Self {
+        self.packages = types;
+        self
+    }
+
+    ///
+    /// import the top and write it as excel
+    ///
+    pub fn import_to(self, excel: PathBuf) -> Result<()> {
+        let config = self.config.clone();
+        let module = self.import()?;
+        ExcelWriter::new(PathBuf::new())
+            .with_config(config)
+            .write_excel(excel, module.wrap_raw())
+    }
+
+    ///
+    /// build top module in the layout of excel
+    ///
+    pub fn import(self) -> Result<VerilogModule> {
+        let parser = VerilogParser::new(&self.file)
+            .with_config(&self.config)
+            .add_includes(self.file.parent().map(|p| p.to_path_buf()).into_iter().collect())
+            .with_library(self.library)
+            .parse()?
+            .with_packages(self.packages)
+            .solve();
+        // the connectivity can not be reproduced without them
+        if !parser.dropped_conns().is_empty() {
+            return Err(Error::Import {
+                file: self.file.clone(),
+                msg: format!(
+                    "connection {} can not be shown in excel, only wire, slice, number & concatenation of them are supported",
+                    parser.dropped_conns().join(", ")
+                ),
+            });
+        }
+        let mut modules = parser.get_module_info();
+        let idx = Self::find_top(&modules, self.module_name.as_deref())
+            .ok_or_else(|| Error::Parse {
+                file: self.file.clone(),
+                msg: format!("can not find top module {}", self.module_name.as_deref().unwrap_or_default()),
+            })?;
+        let top = modules.swap_remove(idx);
+        log::info!("import top module {} from {}", top.module_name, self.file.display());
+
+        let mut module = VerilogModule::new(top.module_name.clone());
+        module.add_param_list(top.param_list.clone());
+
+        // top ports, `assign port = expr` & `assign wire = port` are shown as wire of port
+        let mut assigned = HashSet::new();
+        for p in top.port_list.iter() {
+            let mut new_port = VerilogPort::new(p.inout, &p.name, p.width.clone());
+            new_port.interface = p.interface.clone();
+            new_port.data_type = p.data_type.clone();
+            if !p.info.is_empty() {
+                new_port.set_info_msg(&p.info);
+            }
+            for (lhs, rhs) in top.assign_list.iter() {
+                let connected = if p.inout != PortDir::InPort && lhs == &p.name {
+                    new_port.connect_detached_expr(rhs, &top.param_list).is_ok()
+                } else if p.inout == PortDir::InPort && rhs == &p.name {
+                    new_port.connect_detached_expr(lhs, &top.param_list).is_ok()
+                } else {
+                    false
+                };
+                if connected {
+                    assigned.insert((lhs, rhs));
+                }
+            }
+            module.add_port_inst(new_port.wrap_macro_as(p));
+        }
+        // the connectivity can not be reproduced without them
+        let dropped = top.assign_list
+            .iter()
+            .filter(|(lhs, rhs)| !assigned.contains(&(lhs, rhs)))
+            .map(|(lhs, rhs)| format!("`assign {} = {}`", lhs, rhs))
+            .collect::<Vec<_>>();
+        if !dropped.is_empty() {
+            return Err(Error::Import {
+                file: self.file.clone(),
+                msg: format!("{} can not be shown in excel, only assign between port & wire is supported", dropped.join(", ")),
+            });
+        }
+
+        // one sheet for each module, so every module can be instantiated once
+        let mut insts: HashMap<String, Vec<String>> = HashMap::new();
+        for inst in top.inst_list.iter() {
+            let inst = inst.borrow();
+            insts.entry(inst.module_name.clone()).or_default().push(inst.inst_name.clone().unwrap_or_default());
+        }
+        let mut repeated = insts
+            .iter()
+            .filter(|(_, names)| names.len() > 1)
+            .map(|(module, names)| format!("{} ({})", module, names.join(", ")))
+            .collect::<Vec<_>>();
+        if !repeated.is_empty() {
+            repeated.sort();
+            return Err(Error::Import {
+                file: self.file.clone(),
+                msg: format!("module instantiated more than once can not be shown in excel: {}", repeated.join(", ")),
+            });
+        }
+
+        // instance
+        let wires: HashMap<&String, &Width> = top.wire_list.iter().map(|(n, w)| (n, w)).collect();
+        for inst in top.inst_list.iter() {
+            let mut inst_mut = inst.borrow_mut();
+            let module_name = inst_mut.module_name.clone();
+            for p in inst_mut.port_list.iter_mut() {
+                if p.inout != PortDir::Unknown {
+                    continue;
+                }
+                log::warn!("direction of port {}.{} is unknown, add the definition to the top directory or filelist", module_name, p.name);
+                // width of port without definition is taken from the declared wire
+                if let [_, VerilogValue::UndefinedWire(name)] = p.signals.as_slice()
+                    && let Some(w) = wires.get(name)
+                {
+                    p.width = (*w).clone();
+                }
+            }
+            drop(inst_mut);
+            module.add_inst_module(Arc::clone(inst));
+        }
+        Ok(module)
+    }
+
+    ///
+    /// the module with the given name
+    /// or the last module which is not instantiated by others
+    ///
+    fn find_top(modules: &[VerilogModule], name: Option<&str>) -> Option<usize> {
+        if let Some(name) = name {
+            return modules.iter().position(|m| m.module_name == name);
+        }
+        let used: HashSet<String> = modules
+            .iter()
+            .flat_map(|m| m.inst_list.iter().map(|i| i.borrow().module_name.clone()))
+            .collect();
+        modules.iter().rposition(|m| !used.contains(&m.module_name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::excel::reader::ExcelReader;
+
+    #[test]
+    fn test_import() {
+        let module = TopImporter::new(PathBuf::from("./test/import_top.v")).import().unwrap();
+        assert_eq!(module.module_name, "import_top");
+        assert_eq!(module.port_list.len(), 6);
+        assert_eq!(module.inst_list.len(), 2);
+        assert_eq!(module.inst_list[0].borrow().inst_name.as_deref(), Some("u_fifo"));
+
+        // u_ordered & u_wildcard are the same module as u_named
+        let err = TopImporter::new(PathBuf::from("./test/inst_top.v")).import().unwrap_err();
+        assert!(err.to_string().contains("inst_sub (u_named, u_ordered, u_wildcard)"));
+
+        let err = TopImporter::new(PathBuf::from("./test/conn_top.v")).import().unwrap_err();
+        assert!(err.to_string().contains("u_sub.en: `en & data_in[0]`"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let file = PathBuf::from("./test/import_top.v");
+        let dir = std::env::temp_dir().join(format!("generate_top_import_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let excel = dir.join("import_top.xlsx");
+        let out = dir.join("import_top.v");
+        TopImporter::new(file.clone()).import_to(excel.clone()).unwrap();
+        ExcelReader::new(excel).with_out_path(Some(out.clone())).generate_v().unwrap();
+
+        // the instances of the generated top are connected as the imported one
+        let connections = |f: &PathBuf| {
+            let modules = VerilogParser::new(f).parse().unwrap().solve().get_module_info();
+            let top = modules.into_iter().find(|m| m.module_name == "import_top").unwrap();
+            let mut res = top.inst_list.iter().map(|i| i.borrow().to_inst_string()).collect::<Vec<_>>();
+            res.sort();
+            res
+        };
+        let expected = connections(&file);
+        let generated = connections(&out);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(generated, expected);
+    }
+}
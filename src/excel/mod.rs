@@ -1,2 +1,4 @@
+pub mod diff;
+pub mod import;
 pub mod reader;
 pub mod writer;
@@ -5,14 +5,21 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use calamine::{Data, Range, Reader};
 use regex::Regex;
+use crate::config::{Config, Dialect};
+use crate::error::{CellPos, Error, Result};
 use crate::verilog::data::{VerilogData, WrapMacro};
+use crate::verilog::diagnostic::{Diagnostic, DiagnosticCollector, DiagnosticKind};
 use crate::verilog::module::VerilogModule;
 use crate::verilog::parameter::{Param, ParamValue};
 use crate::verilog::port::{PortDir, UndefineWireCollector, VerilogPort};
 use crate::verilog::wire::WireBuilder;
+use crate::verilog::width::Width;
 
 pub struct ExcelReader {
     path: PathBuf,
+    out_path: Option<PathBuf>,
+    module_name: Option<String>,
+    dialect: Dialect,
 }
 
 impl ExcelReader {
@@ -21,65 +28,113 @@ impl ExcelReader {
     /// 指定excel的路径
     ///
     pub fn new(path: PathBuf) -> Self {
-        ExcelReader { path }
+        ExcelReader { path, out_path: None, module_name: None, dialect: Dialect::default() }
     }
 
-    pub fn generate_v(&self) {
-        let mut module = self.get_excel_info();
-        module.final_check();
-        let parent_path = self.path.parent().expect("Could not get parent path");
+    ///
+    /// read output settings from project config
+    ///
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.dialect = config.output.dialect;
+        self
+    }
+
+    ///
+    /// set the output verilog path, default is `<parent>/<excel stem>.v` (`.sv` for systemverilog)
+    ///
+    pub fn with_out_path(mut self, path: Option<PathBuf>) -> Self {
+        self.out_path = path;
+        self
+    }
+
+    ///
+    /// set the top module name, default is the first sheet name
+    ///
+    pub fn with_module_name(mut self, name: Option<String>) -> Self {
+        self.module_name = name;
+        self
+    }
+
+    fn out_path(&self) -> Result<PathBuf> {
+        if let Some(p) = &self.out_path {
+            return Ok(p.clone());
+        }
+        let parent_path = self.path.parent().ok_or(Error::InvalidPath(self.path.clone()))?;
         let module_name = self.path
             .file_stem()
             .and_then(|s| s.to_str())
-            .expect("Could not get module name");
-        let top_path = parent_path.join(format!("{}.v", module_name));
-        let mut file = File::create(top_path).unwrap();
-        file.write_all(&module.to_module_string().join("\n").as_bytes()).unwrap();
+            .ok_or(Error::InvalidPath(self.path.clone()))?;
+        Ok(parent_path.join(format!("{}.{}", module_name, self.dialect.extension())))
+    }
 
+    pub fn generate_v(&self) -> Result<()> {
+        let mut module = self.get_excel_info()?;
+        module.final_check();
+        // elements of array are not ports, they can not be left as undriven nets
+        if let Some(name) = WireBuilder::undriven_arrays().into_iter().next() {
+            return Err(Error::UndrivenArray(name));
+        }
+        let top_path = self.out_path()?;
+        if let Some(parent) = top_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        let mut file = File::create(&top_path).map_err(|e| Error::io(&top_path, e))?;
+        file.write_all(module.to_module_string(self.dialect).join("\n").as_bytes())
+            .map_err(|e| Error::io(&top_path, e))?;
+        Ok(())
     }
 
-    pub fn get_excel_info(&self) -> VerilogModule {
+    pub fn get_excel_info(&self) -> Result<VerilogModule> {
         log::debug!("Start extract excel file {}", self.path.display());
-        let mut workbook = calamine::open_workbook_auto(&self.path).unwrap();
+        let mut workbook = calamine::open_workbook_auto(&self.path)
+            .map_err(|e| Error::ReadExcel { file: self.path.clone(), source: e })?;
         let sheets = workbook.sheet_names().to_owned();
         if sheets.len() == 0 {
-            log::error!("excel is empty");
-            std::process::exit(1);
+            return Err(Error::EmptyWorkbook(self.path.clone()));
         }
 
         UndefineWireCollector::clear();
         WireBuilder::clear();
+        DiagnosticCollector::clear();
+
+        let mut ranges = Vec::new();
+        for name in sheets.iter() {
+            let range = workbook.worksheet_range(name)
+                .map_err(|e| Error::ReadExcel { file: self.path.clone(), source: e })?;
+            ranges.push(range);
+        }
+        // packed wires & arrays may be sliced in the sheets before its declaration
+        ranges.iter().for_each(Self::register_wire_dims);
 
         let module_name = &sheets[0];
-        let mut module = VerilogModule::new(sheets[0].clone());
+        let mut module = VerilogModule::new(self.module_name.clone().unwrap_or(sheets[0].clone()));
         // extract module ports
-        if let Ok(range) = workbook.worksheet_range(module_name) {
-            log::debug!("Extracting sheet {}", module_name);
-            let (port_list, inst_name, params, _) =
-                Self::extract_port(&range, true, &Vec::new());
-            module.add_ports(port_list);
-            module.add_param_list(params);
-            if let Some(s) = inst_name {
-                module.fix_inst_name(s);
-            }
+        DiagnosticCollector::set_sheet(Some(module_name.as_str()));
+        let range = &ranges[0];
+        log::debug!("Extracting sheet {}", module_name);
+        let (port_list, inst_name, params, _) =
+            self.extract_port(module_name, range, true, &Vec::new())?;
+        module.add_ports(port_list);
+        module.add_param_list(params);
+        if let Some(s) = inst_name {
+            module.fix_inst_name(s);
         }
         module.port_list.iter_mut().for_each(|p| p.register_port_as_wire());
 
         // extract inst module
-        for inst_name in sheets[1..].iter() {
+        for (inst_name, range) in sheets[1..].iter().zip(ranges[1..].iter()) {
             log::debug!("Extracting sheet {}", inst_name);
+            DiagnosticCollector::set_sheet(Some(inst_name.as_str()));
             let mut inst_module = VerilogModule::new(String::from(inst_name));
-            if let Ok(range) = workbook.worksheet_range(inst_name) {
-                let (port_list, inst_name, params, macro_string) =
-                    Self::extract_port(&range, false, &module.param_list);
-                inst_module.add_ports(port_list);
-                inst_module.add_param_list(params);
-                if let Some(s) = inst_name {
-                    inst_module.fix_inst_name(s);
-                }
-                inst_module.port_list.iter_mut().for_each(|p| p.check_health());
-                module.add_inst_module(Arc::new(RefCell::new(inst_module.wrap_macro_with(macro_string))));
+            let (port_list, inst_name, params, macro_string) =
+                self.extract_port(inst_name, range, false, &module.param_list)?;
+            inst_module.add_ports(port_list);
+            inst_module.add_param_list(params);
+            if let Some(s) = inst_name {
+                inst_module.fix_inst_name(s);
             }
+            inst_module.port_list.iter_mut().for_each(|p| p.check_health());
+            module.add_inst_module(Arc::new(RefCell::new(inst_module.wrap_macro_with(macro_string))));
         }
         
         // final check
@@ -88,7 +143,7 @@ impl ExcelReader {
 
         log::debug!("end extract excel file {}", self.path.display());
 
-        module
+        Ok(module)
     }
 
     fn extract_string(data: Option<&Data>) -> Option<String> {
@@ -98,13 +153,103 @@ impl ExcelReader {
         }
     }
 
-    fn extract_width(data: Option<&Data>) -> usize {
+    fn extract_width(data: Option<&Data>, pos: CellPos) -> Result<usize> {
         match data {
-            Some(Data::Int(n)) => n.clone() as usize,
-            Some(Data::String(s)) => s.parse().unwrap(),
-            Some(Data::Float(n)) => n.clone() as usize,
-            _ => 0
+            Some(Data::Int(n)) => Ok(*n as usize),
+            Some(Data::String(s)) => s.trim().parse().map_err(|_| Error::IllegalWidth { pos, value: s.clone() }),
+            Some(Data::Float(n)) => Ok(*n as usize),
+            _ => Ok(0)
+        }
+    }
+
+    ///
+    /// width & unpacked dimensions of port
+    /// packed dimensions are written as `4x8`, unpacked dimensions follow in brackets as `32[4]`
+    /// typed port is written as `my_pkg::req_t(64)`
+    ///
+    fn extract_port_width(data: Option<&Data>, pos: CellPos) -> Result<(Width, Vec<usize>, Option<String>)> {
+        if let Some((data_type, dims)) = data.and_then(Self::split_type) {
+            let (width, unpacked, _) = Self::extract_port_width(Some(&Data::String(dims)), pos)?;
+            return Ok((width, unpacked, Some(data_type)));
         }
+        let (width, unpacked) = match data {
+            // width with undefined macro is written by `ExcelWriter` as `` `WIDTH-1-0+1 ``, it is kept as literal
+            Some(Data::String(s)) if s.contains('`') => Ok((Width::from(s.trim()), Vec::new())),
+            Some(Data::String(s)) if s.contains('x') || s.contains('[') => Self::parse_dims(s)
+                .map(|(packed, unpacked)| (Width::packed(packed), unpacked))
+                .ok_or(Error::IllegalWidth { pos, value: s.clone() }),
+            _ => Ok((Self::extract_width(data, pos)?.into(), Vec::new())),
+        }?;
+        Ok((width, unpacked, None))
+    }
+
+    ///
+    /// `my_pkg::req_t(2x64)` => (`my_pkg::req_t`, `2x64`)
+    ///
+    fn split_type(data: &Data) -> Option<(String, String)> {
+        let Data::String(s) = data else { return None };
+        let re = Regex::new(r"^\s*([a-zA-Z_][\w:]*)\s*\((.*)\)\s*$").unwrap();
+        re.captures(s).map(|c| (c[1].to_string(), c[2].to_string()))
+    }
+
+    ///
+    /// `4x8[2]` => ([4, 8], [2])
+    ///
+    fn parse_dims(s: &str) -> Option<(Vec<usize>, Vec<usize>)> {
+        let (packed, unpacked) = s.split_once('[').map_or((s, ""), |(p, u)| (p, u));
+        let packed = packed.split('x').map(|d| d.trim().parse::<usize>().ok()).collect::<Option<Vec<_>>>()?;
+        let unpacked = unpacked
+            .split('[')
+            .filter(|d| !d.trim().is_empty())
+            .map(|d| d.trim().strip_suffix(']')?.trim().parse::<usize>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        Some((packed, unpacked))
+    }
+
+    ///
+    /// record dimensions of packed wires & arrays, the wire is the port itself if Wire-name is empty
+    /// Wire-name with the full range written by `ExcelWriter`, such as `data[31:0]`, is the whole wire
+    ///
+    fn register_wire_dims(range: &Range<Data>) {
+        let name_re = Regex::new(r"^\s*([a-zA-Z_]\w*)\s*(?:\[\s*(\d+)\s*:\s*0\s*\])?\s*$").unwrap();
+        for row in range.rows() {
+            let (Some(Data::String(port_name)), Some(Data::String(width))) = (row.first(), row.get(2)) else { continue };
+            let (data_type, width) = match Self::split_type(&Data::String(width.clone())) {
+                Some((t, w)) => (Some(t), w),
+                None => (None, width.clone()),
+            };
+            let Some((packed, unpacked)) = Self::parse_dims(&width) else { continue };
+            let name = match row.get(3) {
+                Some(Data::String(s)) if !s.trim().is_empty() => match name_re.captures(s) {
+                    Some(c) if c.get(2).is_none_or(|m| m.as_str().parse::<usize>().ok() == Some(packed.iter().product::<usize>() - 1)) => {
+                        c[1].to_string()
+                    }
+                    _ => continue,
+                },
+                _ => port_name.trim().to_string(),
+            };
+            if packed.len() > 1 {
+                WireBuilder::add_dims(&name, packed);
+            }
+            if !unpacked.is_empty() {
+                WireBuilder::add_array(&name, unpacked);
+            }
+            if let Some(t) = data_type {
+                WireBuilder::add_type(&name, &t);
+            }
+        }
+    }
+
+    ///
+    /// parameter value which is neither number nor parameter of top, it is evaluated in the sheet
+    ///
+    fn extract_param_expr(data: Option<&Data>, params: &[Param]) -> Option<String> {
+        let Some(Data::String(s)) = data else { return None };
+        let s = s.trim();
+        if s.is_empty() || s.parse::<usize>().is_ok() || params.iter().any(|p| p.name == s) {
+            return None;
+        }
+        Some(s.to_string())
     }
 
     fn extract_param(data: Option<&Data>, params: &Vec<Param>) -> ParamValue {
@@ -134,57 +279,92 @@ impl ExcelReader {
         }
     }
 
-    fn match_wires_by_re(port: &mut VerilogPort, wires: Vec<String>, flag: bool) {
+    fn match_wires_by_re(port: &mut VerilogPort, wires: Vec<String>, flag: bool, pos: CellPos) -> Result<()> {
         let name_re = Regex::new(r"\b[a-zA-Z_]\w*\b").unwrap();
         let name_range_re = Regex::new(r"(\b[a-zA-Z_]\w*\b)\s*\[\s*(\d+)\s*:\s*(\d+)\s*]").unwrap();
         let number_re = Regex::new(r"(\d+)'\s*([bodh])\s*([0-9a-fA-F_xzXZ]+)").unwrap();
+        let packed_re = Regex::new(r"^\s*([a-zA-Z_]\w*)\s*\[\s*(\d+)\s*(?::\s*(\d+)\s*)?]\s*$").unwrap();
 
         for wire in wires {
             log::debug!("Match wire `{}`:", wire);
-            if let Some(s) = name_range_re.captures(&wire) {
+            let illegal = || Error::IllegalWire { pos: pos.clone(), value: wire.clone() };
+            // slice of packed wire is on the outer dimension, `data[2]` is data[23:16] for 4x8
+            // the full range written by `ExcelWriter`, such as `data[31:0]`, is the bits of whole wire
+            let full_range = |s: &regex::Captures, dims: &Vec<usize>| {
+                s.get(3).is_some_and(|m| m.as_str() == "0") && s[2].parse::<usize>().ok() == Some(dims.iter().product::<usize>() - 1)
+            };
+            let packed = packed_re
+                .captures(&wire)
+                .and_then(|s| WireBuilder::dims_of(&s[1]).filter(|d| d.len() > 1 && !full_range(&s, d)).map(|d| (s, d)));
+            // element of array, `bank_rd[2]` is a wire of its own
+            let element = packed_re
+                .captures(&wire)
+                .filter(|s| s.get(3).is_none() && WireBuilder::array_of(&s[1]).is_some());
+            if let Some(s) = element {
+                let name = format!("{}[{}]", &s[1], &s[2]);
+                port.connect_undefined_signal(&name, flag);
+                log::debug!("=> Match element {}", name);
+            } else if let Some((s, dims)) = packed {
+                let name = s.get(1).unwrap().as_str();
+                let upper = s[2].parse::<usize>().map_err(|_| illegal())?;
+                let lower = s.get(3).map_or(Ok(upper), |m| m.as_str().parse::<usize>()).map_err(|_| illegal())?;
+                if upper < lower || upper >= dims[0] {
+                    return Err(illegal());
+                }
+                let inner: usize = dims[1..].iter().product();
+                port.connect_partial_signal(name, &(lower * inner..(upper + 1) * inner), flag);
+                log::debug!("=> Match packed {}[{}:{}]", name, upper, lower);
+            } else if let Some(s) = name_range_re.captures(&wire) {
                 let name = s.get(1).unwrap().as_str();
-                let range_end = s.get(2).unwrap().as_str().parse::<usize>().unwrap();
-                let range_start = s.get(3).unwrap().as_str().parse::<usize>().unwrap();
+                let range_end = s.get(2).unwrap().as_str().parse::<usize>().map_err(|_| illegal())?;
+                let range_start = s.get(3).unwrap().as_str().parse::<usize>().map_err(|_| illegal())?;
+                if range_end < range_start {
+                    return Err(illegal());
+                }
                 port.connect_partial_signal(name, &(range_start..(range_end+1)), flag);
                 log::debug!("=> Match range {}[{}:{}]", name, range_end, range_start);
             } else if let Some(s) = number_re.captures(&wire) {
-                let width = s.get(1).unwrap().as_str().parse::<u8>().unwrap();
+                let width = s.get(1).unwrap().as_str().parse::<u8>().map_err(|_| illegal())?;
                 let base = match s.get(2).unwrap().as_str() {
                     "b" => 2,
                     "o" => 8,
                     "h" => 16,
                     _ => 10
                 };
-                let val = u128::from_str_radix(s.get(3).unwrap().as_str(), base).unwrap();
+                let val = u128::from_str_radix(s.get(3).unwrap().as_str(), base).map_err(|_| illegal())?;
                 port.connect_number_signal(val, width);
                 log::debug!("=> Match number {}'d{}", width, val);
             } else if let Some(s) = name_re.find(&wire) {
                 let name = s.as_str();
                 port.connect_undefined_signal(name, flag);
                 log::debug!("=> Match name {}",name);
+            } else {
+                return Err(illegal());
             }
         }
+        Ok(())
     }
 
-    fn check_name_char(name: &str) {
+    fn check_name_char(name: &str, pos: CellPos) -> Result<()> {
         let name_re = Regex::new(r"^\b[a-zA-Z_]\w*\b$").unwrap();
         if !name_re.is_match(name) {
-            panic!("illegal name `{}`!!", name);
+            return Err(Error::IllegalName { pos, name: name.into() });
         }
+        Ok(())
     }
 
-    fn check_name_chars(name: &Vec<String>) {
-        let name_re = Regex::new(r"^\b[a-zA-Z_]\w*\b$").unwrap();
+    fn check_name_chars(name: &Vec<String>, pos: CellPos) -> Result<()> {
         for s in name {
-            if !name_re.is_match(s) {
-                panic!("illegal name `{}`!!", s);
-            }
+            Self::check_name_char(s, pos.clone())?;
         }
+        Ok(())
     }
 
     /// extract message from one sheet
     /// return Portlist & inst_name
-    fn extract_port<'a>(range: &'a Range<Data>, flag: bool, param_list: &Vec<Param>) -> (Vec<VerilogData<VerilogPort>>, Option<&'a String>, Vec<Param>, Vec<String>) {
+    fn extract_port<'a>(&self, sheet: &str, range: &'a Range<Data>, flag: bool, param_list: &Vec<Param>) -> Result<(Vec<VerilogData<VerilogPort>>, Option<&'a String>, Vec<Param>, Vec<String>)> {
+        let (row0, col0) = range.start().unwrap_or((0, 0));
+        let pos = |row: usize, col: u32| CellPos::new(&self.path, sheet, row0 + row as u32, col0 + col);
         let mut port_list = Vec::new();
         let mut inst_name = None;
         let mut params = Vec::new();
@@ -203,43 +383,99 @@ impl ExcelReader {
                         if s.as_str() == "Port-name" {
                             start_port_flag = true;
                             macro_string = Self::extract_wires(row_data.get(5));
-                            Self::check_name_chars(&macro_string);
+                            Self::check_name_chars(&macro_string, pos(row_idx, 5))?;
                         }
                     } else { 
                         let token = Self::extract_string(row_data.get(1));
-                        Self::check_name_char(token.as_ref().unwrap());
+                        let Some(token) = token else { continue };
+                        Self::check_name_char(&token, pos(row_idx, 1))?;
+                        let macro_tags = Self::extract_wires(row_data.get(5));
+                        Self::check_name_chars(&macro_tags, pos(row_idx, 5))?;
+                        // declared type, string, sized number, real & data type of type parameter are kept verbatim
+                        let data_type = Self::extract_string(row_data.get(3));
+                        let literal = match (data_type.as_deref(), row_data.get(2)) {
+                            (Some("type"), Some(Data::String(s))) => Some(ParamValue::Type(s.trim().to_string())),
+                            (_, Some(Data::String(s))) => ParamValue::from_literal(s).filter(|v| matches!(v, ParamValue::Literal(_))),
+                            _ => None,
+                        };
+                        if let Some(value) = literal {
+                            params.push(Param::new_with_param(token, value).with_type(data_type).with_macros(macro_tags));
+                            continue;
+                        }
+                        // expression of module definition, such as `$clog2(DEPTH)`
+                        // expression of top parameters is an override by value
+                        if let Some(expr) = Self::extract_param_expr(row_data.get(2), param_list) {
+                            // the definition is written in column 4 by `ExcelWriter`, an edited expression overrides it
+                            let edited = !flag && Self::extract_string(row_data.get(4)).is_some_and(|d| d.trim() != expr);
+                            let param = match (Param::eval_expr(&expr, &params), Param::eval_expr(&expr, param_list)) {
+                                (Some(v), _) if edited => Param::new(token, v),
+                                (Some(v), _) => Param::new(token, v).with_expr(expr),
+                                (None, Some(v)) => Param::new(token, v),
+                                (None, None) => {
+                                    log::warn!("can not evaluate parameter {} = {}, 0 is used", token, expr);
+                                    Param::new(token, 0).with_expr(expr)
+                                }
+                            };
+                            log::debug!("extract excel parameter is :{:?}", param);
+                            params.push(param.with_type(data_type).with_macros(macro_tags));
+                            continue;
+                        }
                         let value = if param_list.len() > 0 {
                             Self::extract_param(row_data.get(2), param_list)
                         } else {
-                            Self::extract_width(row_data.get(2)).into()
+                            Self::extract_width(row_data.get(2), pos(row_idx, 2))?.into()
                         };
                         log::debug!("extract excel parameter token is :{:?}, value is {:?}", token, value);
-                        params.push(Param::new_with_param(token.unwrap(), value));
+                        params.push(Param::new_with_param(token, value).with_type(data_type).with_macros(macro_tags));
                     }
                     continue;
                 }
                 let port_name = Self::extract_string(row_data.get(0));
-                if port_name.is_none() { continue }
-                Self::check_name_char(port_name.as_ref().unwrap());
+                let Some(port_name) = port_name else { continue };
+                // ports end at the assign section written by `ExcelWriter`
+                if port_name == "Assing-Logic" {
+                    break;
+                }
+                Self::check_name_char(&port_name, pos(row_idx, 0))?;
                 let inout = Self::extract_inout(row_data.get(1));
-                let width = Self::extract_width(row_data.get(2));
+                let (width, unpacked, data_type) = Self::extract_port_width(row_data.get(2), pos(row_idx, 2))?;
                 let wire_name = Self::extract_wires(row_data.get(3));
                 let port_info = Self::extract_string(row_data.get(4));
                 let macro_tags = Self::extract_wires(row_data.get(5));
-                Self::check_name_chars(&macro_string);
+                Self::check_name_chars(&macro_tags, pos(row_idx, 5))?;
 
-                let mut new_port = VerilogPort::new(inout, &port_name.unwrap(), width.into());
+                let mut new_port = VerilogPort::new(inout, &port_name, width);
+                if new_port.width.to_string().contains('`') {
+                    let msg = format!("width of port {} uses undefined macro: {}", port_name, new_port.width);
+                    log::error!("{}", msg);
+                    DiagnosticCollector::report(Diagnostic::new(DiagnosticKind::UnresolvedWidth, &port_name, msg));
+                    new_port.unresolved = true;
+                }
+                new_port.unpacked = unpacked.into_iter().map(Width::from).collect();
+                new_port.data_type = data_type;
                 if let Some(s) = port_info {
                     new_port.set_info_msg(&s);
                 }
-                Self::match_wires_by_re(&mut new_port, wire_name, flag);
+                if new_port.is_interface() {
+                    // `axi_if.master` is kept, `interface` means any interface
+                    if let Some(Data::String(t)) = row_data.get(1) {
+                        new_port.interface = Some(t.trim().into());
+                    }
+                    wire_name.iter().for_each(|w| new_port.connect_interface(w));
+                } else if new_port.unresolved {
+                    if !wire_name.is_empty() {
+                        log::warn!("port {} is not connected to {}, its width is unresolved", port_name, wire_name.join(", "));
+                    }
+                } else {
+                    Self::match_wires_by_re(&mut new_port, wire_name, flag, pos(row_idx, 3))?;
+                }
                 // Dont exec check_health() function, used by the function caller
                 // new_port.check_health();
                 
                 port_list.push(new_port.wrap_macro_with(macro_tags));
             }
         }
-        (port_list, inst_name, params, macro_string)
+        Ok((port_list, inst_name, params, macro_string))
     }
 
 }
@@ -247,13 +483,15 @@ impl ExcelReader {
 
 #[cfg(test)]
 mod test {
+    use crate::error::CellPos;
     use crate::excel::reader::ExcelReader;
     use crate::verilog::port::{PortDir, VerilogPort};
+    use crate::verilog::parameter::Param;
     use crate::verilog::wire::WireBuilder;
 
     // #[test]
     fn test_re() {
-        simple_logger::init_with_level(log::Level::Debug).unwrap();
+        let _ = simple_logger::init_with_level(log::Level::Debug);
         let mut  port = VerilogPort::new(PortDir::InPort, "test_port", 32.into());
         let test_vec = vec![
             "testwire1".to_string(),
@@ -263,18 +501,50 @@ mod test {
             "10'd34".to_string(),
             "8'ha9".to_string()
         ];
-        ExcelReader::match_wires_by_re(&mut port, test_vec, false);
+        let pos = CellPos::new("test.xlsx".as_ref(), "test", 0, 3);
+        ExcelReader::match_wires_by_re(&mut port, test_vec, false, pos).unwrap();
         println!("{:#?}", port.to_inst_string(false));
 
     }
 
     #[test]
     fn test_excel() {
-        simple_logger::init_with_level(log::Level::Debug).unwrap();
+        let _ = simple_logger::init_with_level(log::Level::Debug);
         let file = ExcelReader::new("src/excel/test/uart.xlsx".into());
-        file.generate_v();
+        file.generate_v().unwrap();
         // let module = file.get_excel_info();
         // WireBuilder::builder_show();
         // println!("{:#?}", module);
     }
+
+    #[test]
+    fn test_unresolved_width() {
+        let pos = CellPos::new("test.xlsx".as_ref(), "test", 0, 2);
+        let data = calamine::Data::String("`UNDEF_W-1-0+1".into());
+        let (width, unpacked, data_type) = ExcelReader::extract_port_width(Some(&data), pos).unwrap();
+        assert_eq!(width.to_string(), "`UNDEF_W-1-0+1");
+        assert!(unpacked.is_empty());
+        assert!(data_type.is_none());
+    }
+
+    #[test]
+    fn test_edited_param_expr() {
+        let mut range = calamine::Range::new((0, 0), (5, 5));
+        range.set_value((0, 1), calamine::Data::String("u_sub".into()));
+        range.set_value((2, 1), calamine::Data::String("DEPTH".into()));
+        range.set_value((2, 2), calamine::Data::Int(16));
+        for (row, name, expr) in [(3, "AW", "$clog2(DEPTH)"), (4, "BW", "$clog2(DEPTH) + 1")] {
+            range.set_value((row, 1), calamine::Data::String(name.into()));
+            range.set_value((row, 2), calamine::Data::String(expr.into()));
+            range.set_value((row, 4), calamine::Data::String("$clog2(DEPTH)".into()));
+        }
+        range.set_value((5, 0), calamine::Data::String("Port-name".into()));
+        let top_params = vec![Param::new("DW".into(), 8)];
+        let reader = ExcelReader::new("test.xlsx".into());
+        let (_, _, params, _) = reader.extract_port("sub", &range, false, &top_params).unwrap();
+        // the expression of definition follows the module, the edited one is an override
+        assert!(params[1].is_derived());
+        assert!(!params[2].is_derived());
+        assert_eq!(params[2].get_value(), 5);
+    }
 }
@@ -1,13 +1,21 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use crate::config::{Config, Dialect};
+use crate::error::{Error, Result};
+use crate::excel::diff::ChangeReport;
 use crate::excel::reader::ExcelReader;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use colored::Colorize;
 use regex::Regex;
-use rust_xlsxwriter::{ColNum, Color, Format, FormatAlign, FormatBorder, FormatUnderline, Workbook, Worksheet};
+use rust_xlsxwriter::{ColNum, Color, Format, FormatAlign, FormatBorder, FormatUnderline, Workbook, Worksheet, XlsxError};
 use walkdir::WalkDir;
+use crate::verilog::cache::{hash_packages, CacheEntry, InterfaceCache};
 use crate::verilog::data::{VerilogData, WrapMacro};
+use crate::verilog::diagnostic::{Diagnostic, DiagnosticCollector, DiagnosticKind};
+use crate::verilog::filelist::FileList;
 use crate::verilog::module::VerilogModule;
+use crate::verilog::parameter::Param;
 use crate::verilog::parse::VerilogParser;
 use crate::verilog::port::{UndefineWireCollector, VerilogPort};
 use crate::verilog::wire::WireBuilder;
@@ -16,6 +24,12 @@ use crate::verilog::wire::WireBuilder;
 pub struct ExcelWriter {
     module_dir_path: PathBuf,
     file_list: Vec<PathBuf>,
+
+    excel_path: Option<PathBuf>,
+    module_name: Option<String>,
+    config: Config,
+    filelist: Option<FileList>,
+    dry_run: bool,
 }
 
 impl ExcelWriter {
@@ -26,74 +40,186 @@ impl ExcelWriter {
         }
     }
 
-    fn generate(&self) {
-        let parent_path = self.module_dir_path.parent().expect("Could not get parent path");
-        let module_name = self.module_dir_path
+    ///
+    /// set the project config
+    ///
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    ///
+    /// use filelist as the verilog source set instead of traversing the directory
+    ///
+    pub fn with_filelist(mut self, filelist: Option<FileList>) -> Self {
+        self.filelist = filelist;
+        self
+    }
+
+    ///
+    /// do not write the excel & verilog of sub directories in `traverse_v`
+    /// the verilog generated before is used
+    ///
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    ///
+    /// set the excel path, default is `<parent>/<dirname>.xlsx`
+    ///
+    pub fn with_excel_path(mut self, path: Option<PathBuf>) -> Self {
+        self.excel_path = path;
+        self
+    }
+
+    ///
+    /// set the top module name, default is `<dirname>`
+    ///
+    pub fn with_module_name(mut self, name: Option<String>) -> Self {
+        self.module_name = name;
+        self
+    }
+
+    ///
+    /// verilog source files found by `traverse_v`
+    ///
+    pub fn files(&self) -> &Vec<PathBuf> {
+        &self.file_list
+    }
+
+    fn module_name(&self) -> Result<String> {
+        if let Some(s) = &self.module_name {
+            return Ok(s.clone());
+        }
+        self.module_dir_path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_string())
+            .ok_or(Error::InvalidPath(self.module_dir_path.clone()))
+    }
+
+    fn excel_name(&self) -> Result<PathBuf> {
+        if let Some(p) = &self.excel_path {
+            return Ok(p.clone());
+        }
+        let parent_path = self.module_dir_path.parent().ok_or(Error::InvalidPath(self.module_dir_path.clone()))?;
+        let dir_name = self.module_dir_path
             .file_name()
             .and_then(|s| s.to_str())
-            .expect("Could not get module name");
+            .ok_or(Error::InvalidPath(self.module_dir_path.clone()))?;
+        Ok(parent_path.join(format!("{}.xlsx", dir_name)))
+    }
 
-        let excel_name = parent_path.join(format!("{}.xlsx", module_name));
+    fn generate(&self) -> Result<()> {
+        let module_name = self.module_name()?;
+        let excel_name = self.excel_name()?;
         log::debug!("start generate excel file {}", excel_name.display());
 
-        let module = self.get_module_from_v(module_name);
+        let (module, _) = self.get_module_from_v(&module_name)?;
         WireBuilder::check_health();
 
         // write excel
-        self.write_excel(excel_name, module);
+        self.write_excel(excel_name, module)
     }
 
-    pub fn generate_or_update(&self) {
-        let parent_path = self.module_dir_path.parent().expect("Could not get parent path");
-        let module_name = self.module_dir_path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .expect("Could not get module name");
-        let excel_name = parent_path.join(format!("{}.xlsx", module_name));
+    pub fn generate_or_update(&self) -> Result<()> {
+        let excel_name = self.excel_name()?;
 
         if excel_name.exists() {
             log::debug!("excel {} already exists, next is to update it", excel_name.display());
-            self.update();
+            self.update()?;
             log::info!("excel {} update over!", excel_name.display());
         } else {
             log::debug!("excel {} does not exist, next is to generate it", excel_name.display());
-            self.generate();
+            self.generate()?;
             log::info!("excel {} generate over!", excel_name.display());
         }
+        Ok(())
     }
 
-    fn update(&self) {
-        let parent_path = self.module_dir_path.parent().expect("Could not get parent path");
-        let module_name = self.module_dir_path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .expect("Could not get module name");
-        let excel_name = parent_path.join(format!("{}.xlsx", module_name));
+    fn update(&self) -> Result<()> {
+        let excel_name = self.excel_name()?;
+        let (module, _) = self.reconcile()?;
+        self.write_excel(excel_name, module)
+    }
+
+    ///
+    /// run the same reconciliation as `update`, but save nothing
+    ///
+    pub fn diff(&self) -> Result<ChangeReport> {
+        let (_, report) = self.reconcile()?;
+        Ok(report)
+    }
+
+    ///
+    /// merge the module of rtl into the module of excel
+    /// return the merged module & the changes
+    ///
+    fn reconcile(&self) -> Result<(VerilogData<VerilogModule>, ChangeReport)> {
+        let module_name = self.module_name()?;
+        let module_name = module_name.as_str();
+        let excel_name = self.excel_name()?;
+        let mut report = ChangeReport::default();
         log::info!(">> start to parse verilog source file");
-        let module_v = self.get_module_from_v(module_name);
+        let (module_v, library) = self.get_module_from_v(module_name)?;
+        // findings of parsing rtl are kept, the others are checked again after merging
+        let parse_diags = DiagnosticCollector::diagnostics()
+            .into_iter()
+            .filter(|d| matches!(d.kind, DiagnosticKind::UnresolvedWidth | DiagnosticKind::ParseError))
+            .collect::<Vec<_>>();
         log::info!(">> start to parse excel file");
-        let module_xlsx = self.get_module_from_excel(&excel_name);
+        let module_xlsx = self.get_module_from_excel(&excel_name)?;
 
         UndefineWireCollector::clear();
         WireBuilder::clear();
+        DiagnosticCollector::clear();
+        parse_diags.into_iter().for_each(DiagnosticCollector::report);
         let mut module = VerilogModule::new(module_name.into());
         module.add_param_list(VerilogModule::copy_parameter_from(&module_xlsx));
         // add inst
         for inst_excel in module_xlsx.inst_list.iter() {
             let inst_excel = inst_excel.borrow();
-            if let Some(inst_v) = module_v.find_inst_module_by_name(&inst_excel.module_name) {
+            DiagnosticCollector::set_sheet(Some(inst_excel.module_name.as_str()));
+            // the sheet of excluded module is kept, it is updated by the library definition
+            let inst_v = module_v
+                .find_inst_module_by_name(&inst_excel.module_name)
+                .or_else(|| library.find_inst_module_by_name(&inst_excel.module_name))
+                .or_else(|| self.get_module_from_lib(&inst_excel.module_name));
+            if let Some(inst_v) = inst_v {
                 log::debug!("add inst {} in excel", inst_excel.module_name);
-                let params = VerilogModule::copy_parameter_from(&inst_excel);
-                // update width
+                let mut params = VerilogModule::copy_parameter_from(&inst_excel);
+                // update width, parameters derived from the overridden ones are evaluated again
                 {
                     let mut inst_v_mut = inst_v.borrow_mut();
-                    inst_v_mut.update_literal_port(&params);
+                    // expression differs from the definition is an override by value
+                    for p in params.iter_mut().filter(|p| p.expr.is_some()) {
+                        if inst_v_mut.param_list.iter().any(|d| d.name == p.name && d.expr != p.expr) {
+                            p.expr = None;
+                        }
+                    }
+                    // overrides in excel are kept, the others follow the definition in rtl
+                    params = inst_v_mut.param_list
+                        .iter()
+                        .filter(|d| !d.local)
+                        .map(|d| params.iter().find(|p| p.name == d.name && !p.is_derived()).unwrap_or(d).clone())
+                        .collect();
+                    let width_params = Param::override_params(&inst_v_mut.param_list, &params);
+                    inst_v_mut.update_literal_port(&width_params);
                 }
 
                 // update inst name
                 let inst_v = inst_v.borrow();
+                report.add_param_diff(&inst_excel.module_name, &inst_excel.param_list, &params);
+                report.add_port_diff(
+                    &inst_excel.module_name,
+                    &inst_excel.diff_ports_with(&inst_v).iter().map(|p| &***p).collect::<Vec<_>>(),
+                    &inst_v.diff_ports_with(&inst_excel).iter().map(|p| &***p).collect::<Vec<_>>(),
+                );
                 let mut inst_module = VerilogModule::new(inst_excel.module_name.clone());
-                inst_module.fix_inst_name(inst_excel.inst_name.as_deref().unwrap());
+                inst_module.fix_inst_name(
+                    inst_excel.inst_name.as_deref().or(inst_v.inst_name.as_deref()).unwrap_or_default()
+                );
 
 
                 inst_module.add_param_list(params);
@@ -113,6 +239,7 @@ impl ExcelWriter {
                 module.add_inst_module(Arc::new(RefCell::new(inst_module.wrap_macro_as(&inst_excel))));
             } else {
                 log::info!("Inst {} in excel was not found in rtl, delete it", inst_excel.module_name);
+                report.deleted_insts.push(inst_excel.module_name.clone());
                 continue;
             }
         }
@@ -120,13 +247,16 @@ impl ExcelWriter {
         for inst in module_v.diff_inst_with(&module_xlsx) {
             let inst = inst.borrow();
             log::debug!("add inst {} in rtl", inst.module_name);
+            report.new_insts.push(inst.module_name.clone());
+            DiagnosticCollector::set_sheet(Some(inst.module_name.as_str()));
             let new_module = VerilogModule::copy_module_from(&inst);
             module.add_inst_module(Arc::new(RefCell::new(new_module)));
         }
 
+        DiagnosticCollector::set_sheet(Some(module_name));
         let mut temp_module = VerilogModule::new("temp".into());
-        for (inout, width, name) in WireBuilder::traverse_unload_undriven() {
-            temp_module.add_port(inout, &name, width)
+        for p in self.unconnected_ports() {
+            temp_module.add_port_inst(p.wrap_raw())
         }
 
         // add port
@@ -145,6 +275,8 @@ impl ExcelWriter {
         //     new_port.register_port_as_wire();
         //     module.add_port_inst(new_port);
         // }
+        let mut dropped_ports = Vec::new();
+        let mut added_ports = Vec::new();
         for p in module_xlsx.diff_ports_with(&temp_module) {
             if WireBuilder::find_wire_in(p) {
                 log::debug!("add wire in xlsx but not in rtl: {}", p.name);
@@ -155,71 +287,287 @@ impl ExcelWriter {
             } else {
                 log::debug!("Port {} in xlsx but not in rtl was dropped", p.name);
                 log::info!("{} {}","drop port".bright_black(), p.name.bright_black());
+                dropped_ports.push(&**p);
             }
         }
 
-        for (inout, width, name) in WireBuilder::traverse_unload_undriven() {
-            log::debug!("add port in rtl but not in xlsx: {}", name);
-            log::info!("add port {} by verilog source file", name);
-            let mut new_port = VerilogPort::new(inout, &name, width.into());
+        for mut new_port in self.unconnected_ports() {
+            log::debug!("add port in rtl but not in xlsx: {}", new_port.name);
+            log::info!("add port {} by verilog source file", new_port.name);
+            added_ports.push(VerilogPort::copy_main_port_from(&new_port));
             new_port.register_port_as_wire();
             module.add_port_inst(new_port.wrap_raw());
         }
+        report.add_port_diff(module_name, &dropped_ports, &added_ports.iter().collect::<Vec<_>>());
 
         log::info!("{}", "<======  Change Messages  ======>".bright_purple().bold());
 
         WireBuilder::check_health();
 
-        self.write_excel(excel_name, module.wrap_raw());
-
+        Ok((module.wrap_raw(), report))
     }
 
-    fn write_excel(&self, excel_name: PathBuf, module: VerilogData<VerilogModule>) {
+    ///
+    /// write the top module and its instances, one sheet per module
+    ///
+    pub fn write_excel(&self, excel_name: PathBuf, module: VerilogData<VerilogModule>) -> Result<()> {
         let mut workbook = Workbook::new();
 
-        workbook.push_worksheet(self.add_inst_sheet(&module));
+        workbook.push_worksheet(self.add_inst_sheet(&excel_name, &module)?);
         for item in module.inst_list.iter() {
-            workbook.push_worksheet(self.add_inst_sheet(&*item.borrow()));
+            workbook.push_worksheet(self.add_inst_sheet(&excel_name, &item.borrow())?);
         }
-        workbook.save(excel_name).unwrap();
+        if let Some(parent) = excel_name.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        workbook.save(&excel_name).map_err(|e| Error::WriteExcel { file: excel_name.clone(), source: e })?;
+        Ok(())
     }
 
     ///
     /// get VerilogModule from verilog source file
+    /// modules excluded by config or `// generate_top: skip` are returned as library, they are not instantiated
     ///
-    fn get_module_from_v(&self, module_name: &str) -> VerilogData<VerilogModule> {
+    fn get_module_from_v(&self, module_name: &str) -> Result<(VerilogData<VerilogModule>, VerilogModule)> {
         UndefineWireCollector::clear();
         WireBuilder::clear();
+        DiagnosticCollector::clear();
         let mut module = VerilogModule::new(module_name.into());
+        let mut library = VerilogModule::new(module_name.into());
 
+        // file whose interfaces are cached is not parsed, unless the packages it uses are changed
+        let cache = self.config.cache_dir.as_deref().map(InterfaceCache::new);
+        let mut sources = Vec::new();
         for f in self.file_list.iter() {
-            let inst_module = VerilogParser::new(f)
-                .add_includes(vec![self.module_dir_path.parent().unwrap().to_path_buf()])
-                .parse()
-                .solve()
-                .get_module_info();
+            let parser = self.parser_of(f);
+            let key = cache.as_ref().and_then(|_| parser.cache_key());
+            let entry = cache.as_ref().zip(key.as_deref()).and_then(|(c, k)| c.load(k, f));
+            if entry.is_some() {
+                sources.push((key, entry, parser));
+                continue;
+            }
+            if let Some(parser) = self.parse_file(parser)? {
+                sources.push((key, None, parser));
+            }
+        }
+
+        // types of packages are visible to all the files
+        let packages: HashMap<String, usize> = sources
+            .iter()
+            .flat_map(|(_, entry, parser)| match entry {
+                Some(e) => e.packages.clone(),
+                None => parser.packages(),
+            })
+            .collect();
+        let imported = hash_packages(&packages);
+        for (key, entry, parser) in sources {
+            let cached = entry.is_some();
+            let inst_module = match entry.filter(|e| e.imported == imported) {
+                Some(e) => {
+                    log::debug!("interfaces of file {} are loaded from cache", parser.file().display());
+                    e.modules()
+                }
+                None => {
+                    let parser = if cached {
+                        match self.parse_file(parser)? {
+                            Some(p) => p,
+                            None => continue,
+                        }
+                    } else {
+                        parser
+                    };
+                    let exported = parser.packages();
+                    let file = parser.file();
+                    let modules = parser.with_packages(packages.clone()).solve().get_module_info();
+                    if let (Some(c), Some(k)) = (&cache, &key) {
+                        c.store(k, &CacheEntry::new(file, exported, imported.clone(), &modules));
+                    }
+                    modules
+                }
+            };
             for mut inst_item in inst_module {
-                inst_item.set_default_inst_name();
+                inst_item.set_default_inst_name(&self.config.naming.inst_prefix);
+                if inst_item.skip || !self.config.is_module_selected(&inst_item.module_name) {
+                    log::info!("module {} is excluded, it is not instantiated", inst_item.module_name);
+                    library.add_inst_module(Arc::new(RefCell::new(inst_item.wrap_raw())));
+                    continue;
+                }
                 inst_item.set_default_port_wires();
                 module.add_inst_module(Arc::new(RefCell::new(inst_item.wrap_raw())));
             }
         }
 
         // 遍历wire builder 将所有没有驱动/没有load的信号连接到端口
-        for (inout, width, name) in WireBuilder::traverse_unload_undriven() {
-            let mut new_port = VerilogPort::new(inout, &name, width.into());
+        DiagnosticCollector::set_sheet(Some(module_name));
+        for mut new_port in self.unconnected_ports() {
             new_port.register_port_as_wire();
             module.add_port_inst(new_port.wrap_raw());
         }
 
-        module.wrap_raw()
+        Ok((module.wrap_raw(), library))
+    }
+
+    ///
+    /// top ports of the wires without driver or load
+    /// array is only a port of systemverilog, it is left undriven in verilog
+    ///
+    fn unconnected_ports(&self) -> Vec<VerilogPort> {
+        let mut res = Vec::new();
+        for (inout, width, name) in WireBuilder::traverse_unload_undriven() {
+            let mut port = VerilogPort::new(inout, &name, width.into());
+            port.data_type = WireBuilder::type_of(&name);
+            if let Some(dims) = WireBuilder::array_of(&name) {
+                if self.config.output.dialect != Dialect::SystemVerilog {
+                    log::warn!("array {} is not driven or loaded, unpacked port is only supported by systemverilog", name);
+                    continue;
+                }
+                port.unpacked = dims.into_iter().map(|d| d.into()).collect();
+            }
+            res.push(port);
+        }
+        res
     }
 
-    fn get_module_from_excel(&self, path: &PathBuf) -> VerilogModule {
-        ExcelReader::new(path.clone()).get_excel_info()
+    ///
+    /// parse the file, None if it is skipped by `skip_parse_errors`
+    ///
+    fn parse_file<'a>(&self, parser: VerilogParser<'a>) -> Result<Option<VerilogParser<'a>>> {
+        let file = parser.file();
+        match parser.parse() {
+            Ok(p) => Ok(Some(p)),
+            Err(e) if self.config.skip_parse_errors => {
+                log::warn!("{}", e);
+                log::warn!("file {} is skipped", file.display());
+                DiagnosticCollector::report(Diagnostic::new(
+                    DiagnosticKind::ParseError,
+                    &file.display().to_string(),
+                    e.to_string(),
+                ));
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
     }
 
-    pub fn traverse_v(&mut self) {
+    ///
+    /// library files which may define `module_name`, `<module_name><ext>` in `-y` dirs & `-v` files
+    ///
+    fn library_files_of(&self, module_name: &str) -> Vec<PathBuf> {
+        let mut dirs = self.config.lib_dirs.clone();
+        let mut files = self.config.lib_files.clone();
+        let mut exts = vec![".v".to_string(), ".sv".to_string()];
+        if let Some(fl) = &self.filelist {
+            dirs.extend(fl.lib_dirs.iter().cloned());
+            files.extend(fl.lib_files.iter().cloned());
+            exts = fl.lib_exts.clone();
+        }
+        let mut res = dirs
+            .iter()
+            .flat_map(|d| exts.iter().map(|ext| d.join(format!("{}{}", module_name, ext))))
+            .filter(|p| p.is_file())
+            .collect::<Vec<_>>();
+        res.extend(files);
+        res
+    }
+
+    ///
+    /// all files of `-v` and the files with library extension in `-y` dirs
+    ///
+    pub(crate) fn library_files(&self) -> Vec<PathBuf> {
+        let mut dirs = self.config.lib_dirs.clone();
+        let mut files = self.config.lib_files.clone();
+        let mut exts = vec![".v".to_string(), ".sv".to_string()];
+        if let Some(fl) = &self.filelist {
+            dirs.extend(fl.lib_dirs.iter().cloned());
+            files.extend(fl.lib_files.iter().cloned());
+            exts = fl.lib_exts.clone();
+        }
+        for dir in dirs {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                log::warn!("can not read library dir {}, skip it", dir.display());
+                continue;
+            };
+            let mut found = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .filter(|p| {
+                    let name = p.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                    exts.iter().any(|ext| name.ends_with(ext.as_str()))
+                })
+                .collect::<Vec<_>>();
+            found.sort();
+            files.extend(found);
+        }
+        files
+    }
+
+    ///
+    /// definition of `module_name` in library, it is only used to update the sheet of excel
+    ///
+    fn get_module_from_lib(&self, module_name: &str) -> Option<Arc<RefCell<VerilogData<VerilogModule>>>> {
+        for f in self.library_files_of(module_name) {
+            let parser = match self.parser_of(&f).parse() {
+                Ok(p) => p,
+                Err(e) => {
+                    log::warn!("{}, skip library file {}", e, f.display());
+                    continue;
+                }
+            };
+            let found = parser.solve().get_module_info().into_iter().find(|m| m.module_name == module_name);
+            if let Some(mut module) = found {
+                log::debug!("module {} is found in library file {}", module_name, f.display());
+                module.set_default_inst_name(&self.config.naming.inst_prefix);
+                return Some(Arc::new(RefCell::new(module.wrap_raw())));
+            }
+        }
+        None
+    }
+
+    ///
+    /// create parser with include dirs & defines of config and filelist
+    /// defines of filelist are added first, so they are overridden by config & command line,
+    /// all include dirs are added before the headers of config are preprocessed
+    ///
+    pub(crate) fn parser_of<'a>(&self, file: &'a PathBuf) -> VerilogParser<'a> {
+        let mut includes = Vec::new();
+        if let Some(dir) = self.module_dir_path.parent() {
+            includes.push(dir.to_path_buf());
+        }
+        if let Some(dir) = file.parent() {
+            includes.push(dir.to_path_buf());
+        }
+        let mut parser = VerilogParser::new(file).add_includes(includes);
+        if let Some(fl) = &self.filelist {
+            parser = parser.add_includes(fl.include_dirs.clone());
+            for (name, value) in fl.defines.iter() {
+                parser = parser.add_define(name, value.clone());
+            }
+        }
+        parser.with_config(&self.config)
+    }
+
+    fn get_module_from_excel(&self, path: &Path) -> Result<VerilogModule> {
+        ExcelReader::new(path.to_path_buf()).get_excel_info()
+    }
+
+    pub fn traverse_v(&mut self) -> Result<()> {
+        if let Some(fl) = &self.filelist {
+            log::debug!("Use verilog files in filelist");
+            for f in fl.files.iter() {
+                if self.config.is_excluded(f) {
+                    log::debug!("file {} is excluded by config", f.display());
+                    continue;
+                }
+                self.file_list.push(f.clone());
+            }
+            if !fl.lib_files.is_empty() || !fl.lib_dirs.is_empty() {
+                log::debug!("library files & dirs in filelist are only searched for module definitions");
+            }
+            for item in self.file_list.iter() {
+                log::debug!("file list is {}", item.display());
+            }
+            return Ok(());
+        }
         log::debug!("Traversing verilog files in dir {}", self.module_dir_path.display());
         let mut dir_list = Vec::new();
         let mut excel_list = Vec::new();
@@ -232,6 +580,10 @@ impl ExcelWriter {
                 dir_list.push(entry.into_path());
             } else {
                 let extension = entry.path().extension().unwrap_or_default();
+                if (extension == "v" || extension == "sv") && self.config.is_excluded(entry.path()) {
+                    log::debug!("file {} is excluded by config", entry.path().display());
+                    continue;
+                }
                 if extension == "v" || extension == "sv" {
                     self.file_list.push(entry.clone().into_path());
                 }
@@ -243,15 +595,25 @@ impl ExcelWriter {
 
         for d in dir_list {
             log::debug!("dir list is  {}", d.display());
-            ExcelWriter::new(d.clone()).generate_or_update();
-            let parent = d.parent().expect("Can not get parent name");
+            if !self.dry_run {
+                ExcelWriter::new(d.clone()).with_config(self.config.clone()).generate_or_update()?;
+            }
+            let parent = d.parent().ok_or(Error::InvalidPath(d.clone()))?;
             let file_name = d
                 .file_name()
                 .and_then(|s| s.to_str())
-                .expect("Can not get file name");
+                .ok_or(Error::InvalidPath(d.clone()))?;
             let excel_name = parent.join(format!("{}.xlsx", file_name));
-            ExcelReader::new(excel_name).generate_v();
-            let file_v = parent.join(format!("{}.v", file_name));
+            let file_v = parent.join(format!("{}.{}", file_name, self.config.output.dialect.extension()));
+            if self.dry_run {
+                if file_v.exists() {
+                    self.file_list.push(file_v);
+                } else {
+                    log::warn!("{} is not generated yet, skip it", file_v.display());
+                }
+                continue;
+            }
+            ExcelReader::new(excel_name).with_config(&self.config).generate_v()?;
             self.file_list.push(file_v)
         }
 
@@ -259,9 +621,21 @@ impl ExcelWriter {
         for item in self.file_list.iter() {
             log::debug!("file list is {}", item.display());
         }
+        Ok(())
     }
 
-    fn add_inst_sheet(&self, module: &VerilogData<VerilogModule>) -> Worksheet {
+    ///
+    /// sheet of one module, error is reported with the excel & sheet name
+    ///
+    fn add_inst_sheet(&self, excel_name: &Path, module: &VerilogData<VerilogModule>) -> Result<Worksheet> {
+        Self::fill_sheet(module).map_err(|e| Error::Sheet {
+            file: excel_name.to_path_buf(),
+            sheet: module.module_name.clone(),
+            source: e,
+        })
+    }
+
+    fn fill_sheet(module: &VerilogData<VerilogModule>) -> std::result::Result<Worksheet, XlsxError> {
         let macro_string = module.get_macro_name();
         let mut sheet = Worksheet::new();
         let header_format = Format::new()
@@ -278,65 +652,83 @@ impl ExcelWriter {
             .set_align(FormatAlign::Left);
         let same_wire_port_format = Format::new()
             .set_underline(FormatUnderline::Single);
+        let unresolved_format = Format::new()
+            .set_background_color(Color::Red);
         let title_list = ["Port-name", "InOut", "Width", "Wire-name", "Port-comment", macro_string.as_str()];
         let width_list = [30, 10, 10, 30, 40, 20];
 
         let mut current_line = 0;
 
-        sheet.set_name(&module.module_name).unwrap();
-        sheet.set_row_height(0, 18).unwrap();
-        sheet.set_row_height(1, 20).unwrap();
+        sheet.set_name(&module.module_name)?;
+        sheet.set_row_height(0, 18)?;
+        sheet.set_row_height(1, 20)?;
 
         // write Module Inst name
-        sheet.write_with_format(current_line, 0, "Module Inst Name", &bold_format).unwrap();
-        sheet.write(current_line, 1, format!("{}", module.inst_name.as_deref().unwrap_or_default())).unwrap();
+        sheet.write_with_format(current_line, 0, "Module Inst Name", &bold_format)?;
+        sheet.write(current_line, 1, module.inst_name.as_deref().unwrap_or_default())?;
         current_line += 1;
 
         // write parameter list
-        sheet.write_with_format(current_line, 0, "Parameter:", &bold_format).unwrap();
+        sheet.write_with_format(current_line, 0, "Parameter:", &bold_format)?;
         current_line += 1;
-        for (_idx, para) in module.param_list.iter().enumerate() {
-            sheet.write(current_line, 1, para.name.as_str()).unwrap();
-            sheet.write(current_line, 2, para.get_name()).unwrap();
+        // localparam can not be overridden, it is not shown
+        for para in module.param_list.iter().filter(|p| !p.local) {
+            sheet.write(current_line, 1, para.name.as_str())?;
+            sheet.write(current_line, 2, para.get_name())?;
+            if let Some(t) = &para.data_type {
+                sheet.write(current_line, 3, t)?;
+            }
+            // expression of definition, the parameter is an override when the value is edited
+            if let Some(expr) = &para.expr {
+                sheet.write(current_line, 4, expr)?;
+            }
+            sheet.write(current_line, 5, para.get_macro_name())?;
             current_line += 1;
         }
 
         // write Title
         for item in title_list.into_iter().enumerate() {
-            sheet.write_with_format(current_line, item.0 as ColNum, item.1, &header_format).unwrap();
-            sheet.set_column_width(item.0 as ColNum, width_list[item.0]).unwrap();
+            sheet.write_with_format(current_line, item.0 as ColNum, item.1, &header_format)?;
+            sheet.set_column_width(item.0 as ColNum, width_list[item.0])?;
         }
-        sheet.set_freeze_panes(current_line+1, 0).unwrap();
+        sheet.set_freeze_panes(current_line+1, 0)?;
         current_line += 1;
 
         // write port
         for (_idx, port) in module.port_list.iter().enumerate() {
-            sheet.write(current_line, 0, &port.name).unwrap();
-            sheet.write(current_line, 1, format!("{}", port.inout)).unwrap();
-            // sheet.write_with_format(current_line, 2, port.width.width() as u32, &number_format).unwrap();
-            sheet.write(current_line, 2, port.width.width() as u32).unwrap();
+            sheet.write(current_line, 0, &port.name)?;
+            sheet.write(current_line, 1, port.inout_string())?;
+            // sheet.write_with_format(current_line, 2, port.width.width() as u32, &number_format)?;
+            if port.unresolved {
+                // expression with undefined macro, it must be fixed by hand
+                sheet.write_with_format(current_line, 2, port.width.to_string(), &unresolved_format)?;
+            } else if port.width.is_packed() || port.is_array() || port.data_type.is_some() {
+                sheet.write(current_line, 2, port.width_string())?;
+            } else if !port.is_interface() {
+                sheet.write(current_line, 2, port.width.width() as u32)?;
+            }
             let signal_string = port.get_signal_string()
                 .replace('{', "")
                 .replace('}', "");
             if Self::drop_bracket(&signal_string) == port.name && port.signals.len() == 2 {
-                sheet.write_with_format(current_line, 3, signal_string, &same_wire_port_format).unwrap();
+                sheet.write_with_format(current_line, 3, signal_string, &same_wire_port_format)?;
             } else {
-                sheet.write(current_line, 3, signal_string).unwrap();
+                sheet.write(current_line, 3, signal_string)?;
             }
-            sheet.write(current_line, 4, &port.info).unwrap();
-            sheet.set_row_height(current_line, 16).unwrap();
+            sheet.write(current_line, 4, &port.info)?;
+            sheet.set_row_height(current_line, 16)?;
             
             // write macro tag
-            sheet.write(current_line, 5, port.get_macro_name()).unwrap();
+            sheet.write(current_line, 5, port.get_macro_name())?;
             
             current_line += 1;
         }
         
         // write assign logic
-        sheet.write_with_format(current_line, 0, "Assing-Logic", &header_format).unwrap();
+        sheet.write_with_format(current_line, 0, "Assing-Logic", &header_format)?;
         
 
-        sheet
+        Ok(sheet)
     }
 
     fn drop_bracket(s: &str) -> &str {
@@ -354,14 +746,182 @@ impl ExcelWriter {
 #[cfg(test)]
 mod test {
     use std::path::PathBuf;
+    use crate::error::Error;
+    use crate::config::{Config, Dialect};
+    use crate::excel::reader::ExcelReader;
     use crate::excel::writer::ExcelWriter;
+    use crate::verilog::filelist::FileList;
+    use crate::verilog::data::WrapMacro;
+    use crate::verilog::module::VerilogModule;
+    use crate::verilog::parameter::Param;
 
     #[test]
     fn test_generate() {
-        simple_logger::init_with_level(log::Level::Debug).unwrap();
+        let _ = simple_logger::init_with_level(log::Level::Debug);
         let mut  writer = ExcelWriter::new(PathBuf::from("./src/excel/test/uart"));
         println!("start running");
-        writer.traverse_v();
-        writer.generate_or_update();
+        writer.traverse_v().unwrap();
+        writer.generate_or_update().unwrap();
+    }
+
+    #[test]
+    fn test_library() {
+        let mut config = Config::default();
+        config.lib_dirs.push(PathBuf::from("./test"));
+        config.lib_files.push(PathBuf::from("./test/param_top.sv"));
+        let writer = ExcelWriter::new(PathBuf::from("./src/excel/test/uart")).with_config(config);
+        assert_eq!(writer.library_files_of("skip_pragma")[0], PathBuf::from("./test/skip_pragma.v"));
+
+        let module = writer.get_module_from_lib("param_leaf").unwrap();
+        assert_eq!(module.borrow().port_list.len(), 2);
+        assert!(writer.get_module_from_lib("not_exist").is_none());
+        let files = writer.library_files();
+        assert!(files.contains(&PathBuf::from("./test/param_top.sv")));
+        assert!(files.contains(&PathBuf::from("./test/skip_pragma.v")));
+        assert!(!files.contains(&PathBuf::from("./test/macro_defs.vh")));
+    }
+
+    #[test]
+    fn test_sheet_error() {
+        let writer = ExcelWriter::new(PathBuf::from("./test"));
+        let module = VerilogModule::new("a_module_name_longer_than_31_chars".into()).wrap_raw();
+        let Err(err) = writer.add_inst_sheet(&PathBuf::from("top.xlsx"), &module) else { panic!("sheet name is too long") };
+        assert!(err.to_string().starts_with("can not write sheet `a_module_name_longer_than_31_chars` of excel top.xlsx: "));
+    }
+
+    #[test]
+    fn test_packed_flow() {
+        let dir = std::env::temp_dir().join(format!("generate_top_packed_{}", std::process::id()));
+        let excel = dir.join("packed_flow.xlsx");
+        let out = dir.join("packed_flow.sv");
+        let mut config = Config::default();
+        config.output.dialect = Dialect::SystemVerilog;
+        let filelist = FileList {
+            files: vec![PathBuf::from("./test/types_pkg.sv"), PathBuf::from("./test/packed_flow.sv")],
+            ..Default::default()
+        };
+        let mut writer = ExcelWriter::new(dir.join("packed_flow"))
+            .with_config(config.clone())
+            .with_filelist(Some(filelist))
+            .with_excel_path(Some(excel.clone()));
+        writer.traverse_v().unwrap();
+        writer.generate_or_update().unwrap();
+        ExcelReader::new(excel).with_config(&config).with_out_path(Some(out.clone())).generate_v().unwrap();
+
+        // wires keep the type & packed dimensions of the ports they connect
+        let text = std::fs::read_to_string(&out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let decls = text.lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).collect::<Vec<_>>();
+        assert!(decls.iter().any(|l| l.starts_with("bus_pkg::req_t [1:0] reqs")), "{}", text);
+        assert!(decls.iter().any(|l| l.starts_with("logic [3:0][7:0] data")), "{}", text);
+    }
+
+    #[test]
+    fn test_undriven_array() {
+        let dir = std::env::temp_dir().join(format!("generate_top_array_{}", std::process::id()));
+        let run = |dialect: Dialect| {
+            let excel = dir.join(format!("array_top_{:?}.xlsx", dialect));
+            let out = dir.join(format!("array_top_{:?}.sv", dialect));
+            let mut config = Config::default();
+            config.output.dialect = dialect;
+            let filelist = FileList { files: vec![PathBuf::from("./test/array_sink.sv")], ..Default::default() };
+            let mut writer = ExcelWriter::new(dir.join("array_top"))
+                .with_config(config.clone())
+                .with_filelist(Some(filelist))
+                .with_excel_path(Some(excel.clone()));
+            writer.traverse_v().unwrap();
+            writer.generate_or_update().unwrap();
+            ExcelReader::new(excel).with_config(&config).with_out_path(Some(out.clone())).generate_v()
+                .map(|_| std::fs::read_to_string(&out).unwrap())
+        };
+
+        // array without driver is an unpacked top port of systemverilog
+        let text = run(Dialect::SystemVerilog).unwrap();
+        let decls = text.lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).collect::<Vec<_>>();
+        assert!(decls.iter().any(|l| l.starts_with("input logic") && l.contains("d [0:3]")), "{}", text);
+
+        // and it is refused in verilog instead of an undriven net
+        let res = run(Dialect::Verilog);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(res, Err(Error::UndrivenArray(ref name)) if name == "d"), "{:?}", res);
+    }
+
+    #[test]
+    fn test_unresolved_port() {
+        let dir = std::env::temp_dir().join(format!("generate_top_unresolved_{}", std::process::id()));
+        let excel = dir.join("unresolved_top.xlsx");
+        let out = dir.join("unresolved_top.v");
+        let mut config = Config::default();
+        config.headers.push(PathBuf::from("./test/macro_defs.vh"));
+        let filelist = FileList { files: vec![PathBuf::from("./test/macro_width.v")], ..Default::default() };
+        let mut writer = ExcelWriter::new(dir.join("unresolved_top"))
+            .with_config(config.clone())
+            .with_filelist(Some(filelist))
+            .with_excel_path(Some(excel.clone()));
+        writer.traverse_v().unwrap();
+        writer.generate_or_update().unwrap();
+        ExcelReader::new(excel).with_config(&config).with_out_path(Some(out.clone())).generate_v().unwrap();
+
+        // port of unknown width is left unconnected
+        let text = std::fs::read_to_string(&out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let lines = text.lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).collect::<Vec<_>>();
+        assert!(lines.iter().any(|l| l.starts_with(".dout ( )") && l.contains("undefined macro")), "{}", text);
+        assert!(lines.iter().any(|l| l.starts_with(".din (din")), "{}", text);
+    }
+
+    #[test]
+    fn test_diff_after_update() {
+        let dir = std::env::temp_dir().join(format!("generate_top_diff_{}", std::process::id()));
+        let excel = dir.join("diff_top.xlsx");
+        let writer_of = || {
+            let filelist = FileList { files: vec![PathBuf::from("./test/diff_leaf.sv")], ..Default::default() };
+            let mut writer = ExcelWriter::new(dir.join("diff_top"))
+                .with_filelist(Some(filelist))
+                .with_excel_path(Some(excel.clone()));
+            writer.traverse_v().unwrap();
+            writer
+        };
+        writer_of().generate_or_update().unwrap();
+
+        // override DEPTH and drop STOP_BITS in excel, as the sheet is written before STOP_BITS is added
+        let module = ExcelReader::new(excel.clone()).get_excel_info().unwrap();
+        {
+            let mut leaf = module.inst_list[0].borrow_mut();
+            leaf.param_list.retain(|p| p.name != "STOP_BITS");
+            leaf.param_list[0] = Param::new("DEPTH".into(), 32);
+        }
+        writer_of().write_excel(excel.clone(), module.wrap_raw()).unwrap();
+        let report = writer_of().diff().unwrap();
+        assert_eq!(report.param_changes.len(), 1, "{}", report);
+        assert_eq!(report.param_changes[0].name, "STOP_BITS");
+
+        // changes reported by diff are applied by update, the override is kept
+        writer_of().generate_or_update().unwrap();
+        let report = writer_of().diff().unwrap();
+        let module = ExcelReader::new(excel.clone()).get_excel_info().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(report.param_changes.is_empty(), "{}", report);
+        let leaf = module.inst_list[0].borrow();
+        assert_eq!(leaf.param_list.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["DEPTH", "ADDR_W", "STOP_BITS"]);
+        assert_eq!(leaf.param_list[0].get_value(), 32);
+    }
+
+    #[test]
+    fn test_parser_defines() {
+        let mut config = Config::default();
+        config.defines.push("DATA_W=16".into());
+        let filelist = FileList {
+            defines: vec![("DATA_W".into(), Some("4".into())), ("UNDEF_W".into(), Some("2".into()))],
+            ..Default::default()
+        };
+        let writer = ExcelWriter::new(PathBuf::from("./test"))
+            .with_config(config)
+            .with_filelist(Some(filelist));
+        let file = PathBuf::from("./test/macro_width.v");
+        let module_info = writer.parser_of(&file).parse().unwrap().solve().get_module_info();
+        // define in command line & config overrides the one in filelist
+        assert_eq!(module_info[0].port_list[0].width.width(), 16);
+        assert_eq!(module_info[0].port_list[1].width.width(), 2);
     }
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+//!
+//! generate verilog top module from a spec workbook, and keep the workbook
+//! in sync with the rtl of sub modules
+//!
+//! the `generate_top` binary is a thin wrapper over the functions here
+//!
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub mod config;
+pub mod error;
+pub mod excel;
+pub mod utils;
+pub mod verilog;
+pub mod watch;
+
+pub use crate::config::{Config, Dialect};
+pub use crate::error::{Error, Result};
+pub use crate::excel::diff::ChangeReport;
+pub use crate::excel::import::TopImporter;
+pub use crate::excel::reader::ExcelReader;
+pub use crate::excel::writer::ExcelWriter;
+pub use crate::verilog::data::VerilogData;
+pub use crate::verilog::diagnostic::{Diagnostic, DiagnosticCollector, DiagnosticKind, Severity};
+pub use crate::verilog::filelist::FileList;
+pub use crate::verilog::module::VerilogModule;
+pub use crate::verilog::parse::VerilogParser;
+pub use crate::verilog::port::{PortDir, VerilogPort};
+pub use crate::verilog::wire::WireBuilder;
+
+///
+/// parse a verilog source file into modules
+/// include dirs & defines of `config` are applied
+///
+pub fn parse_rtl(file: &Path, config: &Config) -> Result<Vec<VerilogModule>> {
+    let file = file.to_path_buf();
+    let mut includes = Vec::new();
+    if let Some(dir) = file.parent() {
+        includes.push(dir.to_path_buf());
+    }
+    Ok(VerilogParser::new(&file)
+        .add_includes(includes)
+        .with_config(config)
+        .parse()?
+        .solve()
+        .get_module_info())
+}
+
+///
+/// load a spec workbook, the first sheet is the top module
+/// and the others are instances
+///
+pub fn load_spec(excel: &Path, config: &Config) -> Result<VerilogModule> {
+    ExcelReader::new(excel.to_path_buf())
+        .with_config(config)
+        .with_module_name(config.output.module_name.clone())
+        .get_excel_info()
+}
+
+///
+/// save the top module and its instances as a spec workbook
+///
+pub fn save_spec(module: VerilogData<VerilogModule>, excel: &Path, config: &Config) -> Result<()> {
+    ExcelWriter::new(PathBuf::new())
+        .with_config(config.clone())
+        .write_excel(excel.to_path_buf(), module)
+}
+
+///
+/// run the health check of the module loaded by `load_spec`
+/// return all the findings, the check is passed if there is no error
+///
+pub fn run_checks(module: &mut VerilogModule) -> Vec<Diagnostic> {
+    module.final_check();
+    DiagnosticCollector::diagnostics()
+}
+
+///
+/// render the module as verilog source text
+///
+pub fn render_verilog(module: &VerilogModule, dialect: Dialect) -> String {
+    module.to_module_string(dialect).join("\n")
+}
+
+///
+/// the excel of top directory, default is `<parent>/<dirname>.xlsx`
+///
+pub fn excel_path_of(top_dir: &Path, config: &Config) -> Result<PathBuf> {
+    if let Some(p) = &config.output.excel {
+        return Ok(p.clone());
+    }
+    let parent_path = top_dir.parent().ok_or(Error::InvalidPath(top_dir.to_path_buf()))?;
+    let module_name = top_dir
+        .file_name()
+        .and_then(|s| s.to_str())
+        .ok_or(Error::InvalidPath(top_dir.to_path_buf()))?;
+    Ok(parent_path.join(format!("{}.xlsx", module_name)))
+}
+
+///
+/// generate or update the excel from verilog source files in `top_dir`
+/// or in `filelist` if it is given
+///
+pub fn gen_excel(top_dir: &Path, config: &Config, filelist: Option<FileList>) -> Result<()> {
+    let mut writer = excel_writer_of(top_dir, config, filelist);
+    writer.traverse_v()?;
+    writer.generate_or_update()
+}
+
+pub(crate) fn excel_writer_of(top_dir: &Path, config: &Config, filelist: Option<FileList>) -> ExcelWriter {
+    ExcelWriter::new(top_dir.to_path_buf())
+        .with_config(config.clone())
+        .with_filelist(filelist)
+        .with_excel_path(config.output.excel.clone())
+        .with_module_name(config.output.module_name.clone())
+}
+
+///
+/// preview the changes `gen_excel` would make to the excel, nothing is written
+///
+pub fn diff(top_dir: &Path, config: &Config, filelist: Option<FileList>) -> Result<ChangeReport> {
+    let excel_name = excel_path_of(top_dir, config)?;
+    if !excel_name.exists() {
+        return Err(Error::InvalidPath(excel_name));
+    }
+    let mut writer = excel_writer_of(top_dir, config, filelist).with_dry_run(true);
+    writer.traverse_v()?;
+    writer.diff()
+}
+
+///
+/// convert a hand-written top into the excel of `top_dir`
+/// instance definitions are taken from verilog files of `top_dir` or `filelist`
+/// return the path of generated excel
+///
+pub fn import_top(file: &Path, top_dir: &Path, config: &Config, filelist: Option<FileList>) -> Result<PathBuf> {
+    let excel = excel_path_of(top_dir, config)?;
+    if excel.exists() {
+        return Err(Error::ExcelExists(excel));
+    }
+    let mut writer = excel_writer_of(top_dir, config, filelist).with_dry_run(true);
+    writer.traverse_v()?;
+    // modules defined in `-v` files & `-y` dirs are also used to resolve the instances
+    let mut files = writer.files().to_vec();
+    files.extend(writer.library_files().into_iter().filter(|f| !writer.files().contains(f)));
+    let mut parsers = Vec::new();
+    for f in files.iter().filter(|f| f.as_path() != file) {
+        match writer.parser_of(f).parse() {
+            Ok(parser) => parsers.push(parser),
+            Err(e) => log::warn!("{}, skip it", e),
+        }
+    }
+    let packages: HashMap<String, usize> = parsers.iter().flat_map(|p| p.packages()).collect();
+    let mut library = Vec::new();
+    for parser in parsers {
+        library.extend(parser.with_packages(packages.clone()).solve().get_module_info());
+    }
+    TopImporter::new(file.to_path_buf())
+        .with_config(config)
+        .with_module_name(config.output.module_name.clone())
+        .with_library(library)
+        .with_packages(packages)
+        .import_to(excel.clone())?;
+    Ok(excel)
+}
+
+///
+/// generate verilog top from the excel of `top_dir`
+///
+pub fn from_excel(top_dir: &Path, config: &Config) -> Result<()> {
+    ExcelReader::new(excel_path_of(top_dir, config)?)
+        .with_config(config)
+        .with_out_path(config.output.out.clone())
+        .with_module_name(config.output.module_name.clone())
+        .generate_v()
+}
+
+///
+/// `gen_excel` then `from_excel`
+///
+pub fn from_file(top_dir: &Path, config: &Config, filelist: Option<FileList>) -> Result<()> {
+    gen_excel(top_dir, config, filelist)?;
+    from_excel(top_dir, config)
+}
+
+///
+/// run the same check as `from_excel`, but write nothing
+/// return true if the connectivity is clean
+///
+pub fn check(top_dir: &Path, config: &Config) -> Result<bool> {
+    let excel_name = excel_path_of(top_dir, config)?;
+    if !excel_name.exists() {
+        return Err(Error::InvalidPath(excel_name));
+    }
+    let mut module = load_spec(&excel_name, config)?;
+    let res = module.final_check();
+    if res {
+        log::info!("check passed");
+    } else {
+        log::error!("check failed");
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render() {
+        let mut config = Config::default();
+        config.defines.push("test_macro".into());
+        let modules = parse_rtl(Path::new("./test/npu_afifo_r.sv"), &config).unwrap();
+        let text = render_verilog(&modules[0], Dialect::Verilog);
+        assert!(text.contains(&format!("module {}", modules[0].module_name)));
+        assert!(text.contains("read_valid"));
+    }
+}
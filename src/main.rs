@@ -1,30 +1,102 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use clap::{Parser, Subcommand};
-use crate::excel::reader::ExcelReader;
-use crate::excel::writer::ExcelWriter;
-
-mod excel;
-mod utils;
-mod verilog;
+use generate_top::{Config, Dialect, DiagnosticCollector, Error, FileList, Result};
+use generate_top::watch::Watcher;
 
 
 fn main() {
     simple_logger::init_with_env().unwrap();
     let args = Args::parse();
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+///
+/// run the command, return false if the check is failed
+///
+fn run(args: &Args) -> Result<bool> {
     let module_path = PathBuf::from(args.top.clone());
     if !module_path.is_dir() {
-        panic!("the path {} is not a directory", args.top)
+        return Err(Error::InvalidPath(module_path));
     }
+    let config = load_config(&module_path, args)?;
+    let filelist = args.filelist.as_ref().map(|f| FileList::parse(f)).transpose()?;
 
-    match args.command {
-        Commands::gen_excel => {
-            gen_excel(module_path);
-        }
-        Commands::from_file => {
-            from_file(module_path);
-        }
-        Commands::from_excel => {
-            from_excel(module_path);
+    // the top is imported once, it is not watched
+    if args.watch && !matches!(args.command, Commands::check | Commands::diff | Commands::import_top { .. }) {
+        return Watcher::new(&module_path, &config, filelist)?
+            .with_interval(args.interval.map(Duration::from_millis))
+            .run()
+            .map(|_| true);
+    }
+
+    let res = match &args.command {
+        Commands::gen_excel => generate_top::gen_excel(&module_path, &config, filelist).map(|_| true),
+        Commands::from_file => generate_top::from_file(&module_path, &config, filelist).map(|_| true),
+        Commands::from_excel => generate_top::from_excel(&module_path, &config).map(|_| true),
+        Commands::check => generate_top::check(&module_path, &config),
+        Commands::diff => generate_top::diff(&module_path, &config, filelist).map(|report| {
+            print!("{}", report);
+            true
+        }),
+        Commands::import_top { file } => generate_top::import_top(file, &module_path, &config, filelist).map(|excel| {
+            log::info!("excel {} is generated from {}", excel.display(), file.display());
+            true
+        }),
+    };
+    write_diagnostics(&args.diagnostics_json);
+    res
+}
+
+///
+/// load `generate_top.toml`, the command line options override the file
+///
+fn load_config(module_path: &Path, args: &Args) -> Result<Config> {
+    let mut config = match &args.config {
+        Some(p) => Config::load(p)?,
+        None => Config::load_from(module_path)?,
+    };
+    if args.excel.is_some() {
+        config.output.excel = args.excel.clone();
+    }
+    if args.out.is_some() {
+        config.output.out = args.out.clone();
+    }
+    if args.module_name.is_some() {
+        config.output.module_name = args.module_name.clone();
+    }
+    if let Some(d) = args.dialect {
+        config.output.dialect = d;
+    }
+    // defines of command line are added later, so they override the same name in config
+    config.defines.extend(args.defines.iter().cloned());
+    config.include_dirs.extend(args.include_dirs.iter().cloned());
+    config.lib_dirs.extend(args.lib_dirs.iter().cloned());
+    config.lib_files.extend(args.lib_files.iter().cloned());
+    config.include_modules.extend(args.include_modules.iter().cloned());
+    config.exclude_modules.extend(args.exclude_modules.iter().cloned());
+    if args.cache_dir.is_some() {
+        config.cache_dir = args.cache_dir.clone();
+    }
+    if args.skip_parse_errors {
+        config.skip_parse_errors = true;
+    }
+    config.apply_severity();
+    Ok(config)
+}
+
+fn write_diagnostics(path: &Option<PathBuf>) {
+    if let Some(p) = path {
+        match DiagnosticCollector::write_json(p) {
+            Ok(_) => log::info!("diagnostics written to {}", p.display()),
+            Err(e) => log::error!("failed to write diagnostics {}: {}", p.display(), e),
         }
     }
 }
@@ -39,6 +111,74 @@ struct Args {
     /// indicate the top module directory
     #[arg(short, long)]
     top: String,
+
+    /// excel file path, default is `<parent>/<top dirname>.xlsx`
+    #[arg(long, value_name = "FILE")]
+    excel: Option<PathBuf>,
+
+    /// generated verilog file path, default is `<excel dir>/<excel name>.v`
+    #[arg(long, value_name = "FILE")]
+    out: Option<PathBuf>,
+
+    /// top module name, default is the top dirname
+    #[arg(long, value_name = "NAME")]
+    module_name: Option<String>,
+
+    /// output dialect of generated top
+    #[arg(long, value_enum)]
+    dialect: Option<Dialect>,
+
+    /// preprocessor define, can be repeated
+    #[arg(short = 'D', long = "define", value_name = "NAME[=VALUE]")]
+    defines: Vec<String>,
+
+    /// include directory of verilog source files, can be repeated
+    #[arg(short = 'I', long = "include", value_name = "DIR")]
+    include_dirs: Vec<PathBuf>,
+
+    /// library directory searched for `<module>.v` & `<module>.sv`, can be repeated
+    #[arg(short = 'y', value_name = "DIR")]
+    lib_dirs: Vec<PathBuf>,
+
+    /// library file searched for module definitions, can be repeated
+    #[arg(short = 'v', value_name = "FILE")]
+    lib_files: Vec<PathBuf>,
+
+    /// simulator style filelist used as the verilog source set
+    #[arg(short = 'f', long, value_name = "FILE")]
+    filelist: Option<PathBuf>,
+
+    /// config file, default is `generate_top.toml` found from the top directory upward
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// write health check findings as json to this file
+    #[arg(long, value_name = "FILE")]
+    diagnostics_json: Option<PathBuf>,
+
+    /// module instantiated in top, glob pattern is supported, can be repeated
+    #[arg(long = "include-module", value_name = "PATTERN")]
+    include_modules: Vec<String>,
+
+    /// module not instantiated in top, glob pattern is supported, can be repeated
+    #[arg(long = "exclude-module", value_name = "PATTERN")]
+    exclude_modules: Vec<String>,
+
+    /// cache parsed module interfaces in this directory, unchanged files are not parsed again
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// skip verilog files which can not be parsed, with warning
+    #[arg(long)]
+    skip_parse_errors: bool,
+
+    /// keep running, update excel when rtl changes and regenerate top when excel changes
+    #[arg(long)]
+    watch: bool,
+
+    /// poll interval of `--watch` in milliseconds, default is 1000
+    #[arg(long, value_name = "MS", requires = "watch")]
+    interval: Option<u64>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -50,26 +190,17 @@ enum Commands {
     from_file,
 
     /// generate verilog-top from excel
-    from_excel
-}
+    from_excel,
 
-fn gen_excel(path: PathBuf) {
-    let mut writer = ExcelWriter::new(path);
-    writer.traverse_v();
-    writer.generate_or_update();
-}
+    /// check connectivity of excel without writing any file
+    check,
 
-fn from_excel(path: PathBuf) {
-    let parent_path = path.parent().expect("Could not get parent path");
-    let module_name = path
-        .file_name()
-        .and_then(|s| s.to_str())
-        .expect("Could not get module name");
-    let excel_name = parent_path.join(format!("{}.xlsx", module_name));
-    ExcelReader::new(excel_name).generate_v();
-}
+    /// preview the changes of `gen_excel` without writing any file
+    diff,
 
-fn from_file(path: PathBuf) {
-    gen_excel(path.clone());
-    from_excel(path);
-}
\ No newline at end of file
+    /// convert a hand-written verilog top into excel
+    import_top {
+        /// verilog file of the top module
+        file: PathBuf,
+    },
+}
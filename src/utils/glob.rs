@@ -0,0 +1,42 @@
+///
+/// simple glob matcher, support `*` and `?`
+///
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p = pattern.chars().collect::<Vec<_>>();
+    let t = text.chars().collect::<Vec<_>>();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    while pi < p.len() && p[pi] == '*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        assert!(glob_match("*_tb.v", "uart_tb.v"));
+        assert!(glob_match("uart_?x.v", "uart_rx.v"));
+        assert!(!glob_match("*_tb.v", "uart_rx.v"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+    }
+}
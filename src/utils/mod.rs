@@ -1,2 +1,3 @@
 pub mod calculator;
 pub mod solve_func;
+pub mod glob;
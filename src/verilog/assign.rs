@@ -3,5 +3,5 @@ use crate::verilog::wire::VerilogWire;
 
 struct VerilogAssign {
     left: VerilogData<VerilogWire>,
-    right: 
+    right: VerilogData<VerilogWire>,
 }
\ No newline at end of file
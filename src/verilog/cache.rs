@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::verilog::data::{VerilogData, WrapMacro};
+use crate::verilog::module::VerilogModule;
+use crate::verilog::parameter::Param;
+use crate::verilog::port::{PortDir, VerilogPort};
+use crate::verilog::width::Width;
+
+/// changed when the format of cache entry is changed
+const CACHE_VERSION: u32 = 2;
+
+///
+/// on-disk cache of module interfaces, one json file for each verilog file
+/// the file is named by `VerilogParser::cache_key`
+///
+pub struct InterfaceCache {
+    dir: PathBuf,
+}
+
+impl InterfaceCache {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    ///
+    /// None if there is no entry, or it is written by other version or for other source
+    ///
+    pub fn load(&self, key: &str, source: &Path) -> Option<CacheEntry> {
+        let path = self.path_of(key);
+        let text = std::fs::read_to_string(&path).ok()?;
+        let len = std::fs::metadata(source).ok()?.len();
+        match serde_json::from_str::<CacheEntry>(&text) {
+            Ok(entry) if entry.version == CACHE_VERSION && entry.source == source && entry.len == len => Some(entry),
+            Ok(_) => None,
+            Err(e) => {
+                log::debug!("cache {} is broken: {}, ignore it", path.display(), e);
+                None
+            }
+        }
+    }
+
+    ///
+    /// failure of writing cache is not an error, the file is parsed again next time
+    ///
+    pub fn store(&self, key: &str, entry: &CacheEntry) {
+        let path = self.path_of(key);
+        let res = std::fs::create_dir_all(&self.dir).and_then(|_| {
+            let text = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+            std::fs::write(&path, text)
+        });
+        if let Err(e) = res {
+            log::warn!("can not write cache {}: {}", path.display(), e);
+        }
+    }
+
+    fn path_of(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+///
+/// ports & parameters of the modules in one file
+///
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry {
+    version: u32,
+    /// path & length of the verilog file, checked with the key when loaded
+    source: PathBuf,
+    len: u64,
+    /// types of packages in this file, they are visible to other files
+    pub packages: HashMap<String, usize>,
+    /// hash of the package types used to solve this file, see `hash_packages`
+    pub imported: String,
+    modules: Vec<CachedModule>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedModule {
+    name: String,
+    skip: bool,
+    params: Vec<Param>,
+    ports: Vec<CachedPort>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedPort {
+    /// `ifdef macros from outer to inner
+    macros: Vec<String>,
+    inout: PortDir,
+    name: String,
+    width: Width,
+    info: String,
+    interface: Option<String>,
+    unpacked: Vec<Width>,
+    unresolved: bool,
+    data_type: Option<String>,
+}
+
+impl CachedPort {
+    fn from_port(data: &VerilogData<VerilogPort>) -> Self {
+        let p = data.get_raw();
+        Self {
+            macros: data.macros(),
+            inout: p.inout,
+            name: p.name.clone(),
+            width: p.width.clone(),
+            info: p.info.clone(),
+            interface: p.interface.clone(),
+            unpacked: p.unpacked.clone(),
+            unresolved: p.unresolved,
+            data_type: p.data_type.clone(),
+        }
+    }
+
+    fn into_port(self) -> VerilogData<VerilogPort> {
+        let mut port = VerilogPort::new(self.inout, &self.name, self.width);
+        port.info = self.info;
+        port.interface = self.interface;
+        port.unpacked = self.unpacked;
+        port.unresolved = self.unresolved;
+        port.data_type = self.data_type;
+        port.wrap_macro_with(self.macros.into_iter().rev().collect())
+    }
+}
+
+impl CacheEntry {
+    pub fn new(source: &Path, packages: HashMap<String, usize>, imported: String, modules: &[VerilogModule]) -> Self {
+        let modules = modules
+            .iter()
+            .map(|m| CachedModule {
+                name: m.module_name.clone(),
+                skip: m.skip,
+                params: m.param_list.clone(),
+                ports: m.port_list.iter().map(CachedPort::from_port).collect(),
+            })
+            .collect();
+        let len = std::fs::metadata(source).map(|m| m.len()).unwrap_or_default();
+        Self { version: CACHE_VERSION, source: source.to_path_buf(), len, packages, imported, modules }
+    }
+
+    ///
+    /// modules with ports & parameters only, instances & assigns are not cached
+    ///
+    pub fn modules(self) -> Vec<VerilogModule> {
+        self.modules
+            .into_iter()
+            .map(|m| {
+                let mut module = VerilogModule::new(m.name);
+                module.skip = m.skip;
+                module.add_param_list(m.params);
+                module.add_ports(m.ports.into_iter().map(CachedPort::into_port).collect());
+                module
+            })
+            .collect()
+    }
+}
+
+///
+/// hash of package types, a cached file is solved again when it is changed
+///
+pub fn hash_packages(packages: &HashMap<String, usize>) -> String {
+    let mut items = packages.iter().collect::<Vec<_>>();
+    items.sort();
+    let mut digest = Digest::new();
+    for (name, width) in items {
+        digest.update(name).update(width.to_string());
+    }
+    digest.finish()
+}
+
+///
+/// 128 bits FNV-1a digest, it is stable between builds & platforms unlike `DefaultHasher`
+///
+pub struct Digest(u128);
+
+impl Digest {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    pub fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+
+    ///
+    /// length is hashed before the bytes, so `("ab", "c")` differs from `("a", "bc")`
+    ///
+    pub fn update(&mut self, data: impl AsRef<[u8]>) -> &mut Self {
+        let data = data.as_ref();
+        for b in (data.len() as u64).to_le_bytes().iter().chain(data) {
+            self.0 ^= *b as u128;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+        self
+    }
+
+    pub fn finish(&self) -> String {
+        format!("{:032x}", self.0)
+    }
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::verilog::parse::VerilogParser;
+
+    #[test]
+    fn test_round_trip() {
+        let file = PathBuf::from("./test/param_top.sv");
+        let parser = VerilogParser::new(&file);
+        let key = parser.cache_key().unwrap();
+        let modules = parser.parse().unwrap().solve().get_module_info();
+
+        let dir = std::env::temp_dir().join(format!("generate_top_cache_test_{}", std::process::id()));
+        let cache = InterfaceCache::new(&dir);
+        let imported = hash_packages(&HashMap::new());
+        cache.store(&key, &CacheEntry::new(&file, HashMap::new(), imported.clone(), &modules));
+        // entry of other source is not used
+        assert!(cache.load(&key, Path::new("./test/inst_top.v")).is_none());
+        let entry = cache.load(&key, &file).unwrap();
+        assert_eq!(entry.imported, imported);
+        let cached = entry.modules();
+        assert_eq!(cached.len(), modules.len());
+        assert_eq!(cached[0].module_name, "param_leaf");
+        assert_eq!(cached[0].param_list[1].get_name(), "$clog2(DEPTH)");
+        assert_eq!(cached[0].port_list[0].width.width(), modules[0].port_list[0].width.width());
+        assert_eq!(cached[0].port_list[0].to_port_string(false, Default::default()), modules[0].port_list[0].to_port_string(false, Default::default()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_digest() {
+        // value must not change between builds, it is the name of cache file
+        assert_eq!(Digest::new().finish(), "6c62272e07bb014262b821756295c58d");
+        assert_ne!(Digest::new().update("ab").update("c").finish(), Digest::new().update("a").update("bc").finish());
+    }
+}
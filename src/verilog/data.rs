@@ -5,6 +5,7 @@ use serde_json::value::Index;
 use strum::Display;
 use crate::verilog::module::VerilogModule;
 use crate::verilog::port::VerilogPort;
+use crate::config::Dialect;
 
 ///
 /// type T may be VerilogModule, VerilogPort, VerilogWire
@@ -39,6 +40,20 @@ impl<T> VerilogData<T> {
         res.retain(|x| !x.is_empty());
         res.join(", ")
     }
+
+    ///
+    /// macros from outer to inner
+    ///
+    pub fn macros(&self) -> Vec<String> {
+        match self {
+            VerilogData::Raw(_) => Vec::new(),
+            VerilogData::Macro { name, value } => {
+                let mut res = vec![name.clone()];
+                res.extend(value.macros());
+                res
+            }
+        }
+    }
 }
 
 impl VerilogData<VerilogModule> {
@@ -73,15 +88,15 @@ impl VerilogData<VerilogPort> {
         }
     }
 
-    pub fn to_port_string(&self, is_last: bool) -> Vec<String> {
+    pub fn to_port_string(&self, is_last: bool, dialect: Dialect) -> Vec<String> {
         match self {
             VerilogData::Raw(x) => {
-                x.to_port_string(is_last)
+                x.to_port_string(is_last, dialect)
             }
             VerilogData::Macro { name, value } => {
                 let mut res = Vec::new();
                 res.push(format!("`ifdef {}", name));
-                res.extend(value.to_port_string(is_last));
+                res.extend(value.to_port_string(is_last, dialect));
                 res.push(format!("`endif  // {}", name));
                 res
             }
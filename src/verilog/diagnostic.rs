@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use crate::error::{Error, Result};
+
+///
+/// findings of health check
+/// collected by `WireBuilder::check_health`, `VerilogPort::check_connected`
+/// and `UndefineWireCollector::solve_func`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum DiagnosticKind {
+    Undriven,
+    Unload,
+    MultiDriver,
+    UnderConnected,
+    OverConnected,
+    UnresolvedWire,
+    /// port width uses undefined macro
+    UnresolvedWidth,
+    /// verilog file can not be parsed, it is skipped
+    ParseError,
+}
+
+impl DiagnosticKind {
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::Undriven
+            | DiagnosticKind::MultiDriver
+            | DiagnosticKind::UnresolvedWire
+            | DiagnosticKind::UnresolvedWidth => Severity::Error,
+            DiagnosticKind::Unload
+            | DiagnosticKind::UnderConnected
+            | DiagnosticKind::OverConnected
+            | DiagnosticKind::ParseError => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    /// finding is dropped
+    Ignore,
+}
+
+///
+/// the sheet & port which a wire is connected to
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Endpoint {
+    pub sheet: Option<String>,
+    pub port: String,
+}
+
+impl Endpoint {
+    pub fn new(sheet: Option<String>, port: &str) -> Self {
+        Self { sheet, port: port.into() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BitRange {
+    pub msb: usize,
+    pub lsb: usize,
+}
+
+impl From<&Range<usize>> for BitRange {
+    fn from(value: &Range<usize>) -> Self {
+        Self { msb: value.end - 1, lsb: value.start }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    pub wire: String,
+    pub range: Option<BitRange>,
+    pub sources: Vec<Endpoint>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, wire: &str, message: String) -> Self {
+        Self {
+            kind,
+            severity: kind.default_severity(),
+            wire: wire.into(),
+            range: None,
+            sources: Vec::new(),
+            message,
+        }
+    }
+
+    pub fn with_range(mut self, range: &Range<usize>) -> Self {
+        self.range = Some(range.into());
+        self
+    }
+
+    pub fn with_sources(mut self, sources: Vec<Endpoint>) -> Self {
+        self.sources = sources;
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct DiagnosticReport<'a> {
+    errors: usize,
+    warnings: usize,
+    diagnostics: &'a Vec<Diagnostic>,
+}
+
+#[derive(Default)]
+pub struct DiagnosticCollector {
+    items: Vec<Diagnostic>,
+    sheet: Option<String>,
+    severity: HashMap<DiagnosticKind, Severity>,
+}
+
+static DIAGNOSTIC_COLLECTOR: LazyLock<Mutex<DiagnosticCollector>> = LazyLock::new(|| {
+    Mutex::new(DiagnosticCollector {
+        ..Default::default()
+    })
+});
+
+impl DiagnosticCollector {
+    ///
+    /// set the sheet which is being processed
+    /// ports created after this call will record it as their source
+    ///
+    pub fn set_sheet(sheet: Option<&str>) {
+        let mut collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        collector.sheet = sheet.map(|s| s.to_string());
+    }
+
+    pub fn current_sheet() -> Option<String> {
+        let collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        collector.sheet.clone()
+    }
+
+    ///
+    /// override the default severity of a finding kind
+    ///
+    pub fn set_severity(kind: DiagnosticKind, severity: Severity) {
+        let mut collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        collector.severity.insert(kind, severity);
+    }
+
+    pub fn report(mut diag: Diagnostic) {
+        let mut collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        if let Some(s) = collector.severity.get(&diag.kind) {
+            diag.severity = *s;
+        }
+        if diag.severity != Severity::Ignore {
+            collector.items.push(diag);
+        }
+    }
+
+    pub fn error_count() -> usize {
+        let collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        collector.items.iter().filter(|d| d.severity == Severity::Error).count()
+    }
+
+    pub fn diagnostics() -> Vec<Diagnostic> {
+        let collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        collector.items.clone()
+    }
+
+    ///
+    /// write all the findings as json
+    ///
+    pub fn write_json(path: &Path) -> Result<()> {
+        let collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        let warnings = collector.items.iter().filter(|d| d.severity == Severity::Warning).count();
+        let report = DiagnosticReport {
+            errors: collector.items.len() - warnings,
+            warnings,
+            diagnostics: &collector.items,
+        };
+        let text = serde_json::to_string_pretty(&report)
+            .map_err(|e| Error::io(path, e.into()))?;
+        let mut file = File::create(path).map_err(|e| Error::io(path, e))?;
+        file.write_all(text.as_bytes()).map_err(|e| Error::io(path, e))?;
+        Ok(())
+    }
+
+    pub fn clear() {
+        let mut collector = DIAGNOSTIC_COLLECTOR.lock().unwrap();
+        collector.items = Vec::new();
+        collector.sheet = None;
+    }
+}
+
+///
+/// merge sorted bits into continuous ranges
+///
+pub fn bit_ranges(bits: &[usize]) -> Vec<Range<usize>> {
+    let mut res: Vec<Range<usize>> = Vec::new();
+    for &bit in bits {
+        match res.last_mut() {
+            Some(r) if r.end == bit => r.end = bit + 1,
+            _ => res.push(bit..bit + 1),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bit_ranges() {
+        assert_eq!(bit_ranges(&[0, 1, 2, 5, 7, 8]), vec![0..3, 5..6, 7..9]);
+        assert!(bit_ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_json() {
+        let diag = Diagnostic::new(DiagnosticKind::Undriven, "wire1", "wire1[3:0] has load but no driver".into())
+            .with_range(&(0..4))
+            .with_sources(vec![Endpoint::new(Some("uart_tx".into()), "prescale")]);
+        let s = serde_json::to_string(&diag).unwrap();
+        assert!(s.contains("\"kind\":\"undriven\""));
+        assert!(s.contains("\"msb\":3"));
+        assert!(s.contains("\"sheet\":\"uart_tx\""));
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::error::{Error, Result};
+
+/// `$VAR`, `${VAR}` and `$(VAR)` in filelist
+static ENV_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\$(?:\{(\w+)\}|\((\w+)\)|(\w+))").unwrap());
+
+const MAX_NEST_DEPTH: usize = 16;
+
+///
+/// simulator style filelist (`.f`)
+/// support `-f`, `-F`, `+incdir+`, `+define+`, `-v`, `-y` and `+libext+`
+///
+#[derive(Debug, Default, Clone)]
+pub struct FileList {
+    pub files: Vec<PathBuf>,
+    pub include_dirs: Vec<PathBuf>,
+    pub defines: Vec<(String, Option<String>)>,
+    pub lib_files: Vec<PathBuf>,
+    pub lib_dirs: Vec<PathBuf>,
+    pub lib_exts: Vec<String>,
+}
+
+impl FileList {
+    ///
+    /// parse filelist, relative paths are based on current directory
+    /// like `-f` in simulator
+    ///
+    pub fn parse(path: &Path) -> Result<FileList> {
+        let mut res = FileList::default();
+        let cwd = std::env::current_dir().map_err(|e| Error::io(path, e))?;
+        res.parse_file(path, &cwd, 0)?;
+        if res.lib_exts.is_empty() {
+            res.lib_exts = vec![".v".into(), ".sv".into()];
+        }
+        Ok(res)
+    }
+
+    fn parse_file(&mut self, path: &Path, base: &Path, depth: usize) -> Result<()> {
+        let err = |msg: String| Error::FileList { file: path.to_path_buf(), msg };
+        if depth > MAX_NEST_DEPTH {
+            return Err(err("filelist is nested too deep".into()));
+        }
+        log::debug!("parse filelist {}", path.display());
+        let text = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let mut tokens = Self::tokenize(&text).into_iter();
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "-f" | "-F" => {
+                    let next = tokens.next().ok_or_else(|| err(format!("missing file after {}", token)))?;
+                    let nested = Self::resolve(base, &next);
+                    // `-F` is relative to the directory of nested filelist
+                    let nested_base = if token == "-F" {
+                        nested.parent().unwrap_or(Path::new(".")).to_path_buf()
+                    } else {
+                        base.to_path_buf()
+                    };
+                    self.parse_file(&nested, &nested_base, depth + 1)?;
+                }
+                "-v" => {
+                    let next = tokens.next().ok_or_else(|| err("missing file after -v".into()))?;
+                    self.lib_files.push(Self::resolve(base, &next));
+                }
+                "-y" => {
+                    let next = tokens.next().ok_or_else(|| err("missing dir after -y".into()))?;
+                    self.lib_dirs.push(Self::resolve(base, &next));
+                }
+                t if t.starts_with("+incdir+") => {
+                    for dir in t["+incdir+".len()..].split('+').filter(|s| !s.is_empty()) {
+                        self.include_dirs.push(Self::resolve(base, dir));
+                    }
+                }
+                t if t.starts_with("+define+") => {
+                    for def in t["+define+".len()..].split('+').filter(|s| !s.is_empty()) {
+                        match def.split_once('=') {
+                            Some((name, value)) => self.defines.push((name.into(), Some(value.into()))),
+                            None => self.defines.push((def.into(), None)),
+                        }
+                    }
+                }
+                t if t.starts_with("+libext+") => {
+                    for ext in t["+libext+".len()..].split('+').filter(|s| !s.is_empty()) {
+                        self.lib_exts.push(ext.into());
+                    }
+                }
+                t if t.starts_with('-') || t.starts_with('+') => {
+                    log::debug!("ignore option `{}` in filelist {}", t, path.display());
+                }
+                t => {
+                    self.files.push(Self::resolve(base, t));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// remove comments and split by whitespace
+    ///
+    fn tokenize(text: &str) -> Vec<String> {
+        let block_comment = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+        let text = block_comment.replace_all(text, " ");
+        let mut res = Vec::new();
+        for line in text.lines() {
+            let line = line.split("//").next().unwrap_or_default();
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            res.extend(line.split_whitespace().map(Self::expand_env));
+        }
+        res
+    }
+
+    ///
+    /// expand `$VAR`, `${VAR}` and `$(VAR)`
+    ///
+    fn expand_env(s: &str) -> String {
+        ENV_RE
+            .replace_all(s, |caps: &regex::Captures| {
+                let name = caps.get(1).or(caps.get(2)).or(caps.get(3)).unwrap().as_str();
+                std::env::var(name).unwrap_or_else(|_| {
+                    log::warn!("environment variable {} is not set", name);
+                    String::new()
+                })
+            })
+            .to_string()
+    }
+
+    fn resolve(base: &Path, s: &str) -> PathBuf {
+        let p = PathBuf::from(s);
+        if p.is_absolute() { p } else { base.join(p) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_filelist() {
+        let dir = std::env::temp_dir().join("generate_top_filelist_test");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/sub.f"), "// nested list\nb.v\n+define+B_WIDTH=4\n").unwrap();
+        std::fs::write(
+            dir.join("top.f"),
+            "# top list\n+incdir+inc+inc2\n+define+SIM+WIDTH=8\na.v\n-F sub/sub.f\n-v lib/cell.v\n-y lib\n+libext+.v\n-sv\n",
+        ).unwrap();
+
+        let mut fl = FileList::default();
+        fl.parse_file(&dir.join("top.f"), &dir, 0).unwrap();
+        assert_eq!(fl.include_dirs, vec![dir.join("inc"), dir.join("inc2")]);
+        assert_eq!(fl.defines, vec![
+            ("SIM".to_string(), None),
+            ("WIDTH".to_string(), Some("8".to_string())),
+            ("B_WIDTH".to_string(), Some("4".to_string())),
+        ]);
+        assert_eq!(fl.files, vec![dir.join("a.v"), dir.join("sub/b.v")]);
+        assert_eq!(fl.lib_files, vec![dir.join("lib/cell.v")]);
+        assert_eq!(fl.lib_dirs, vec![dir.join("lib")]);
+    }
+}
@@ -8,6 +8,9 @@ pub mod parameter;
 pub mod width;
 pub mod assign;
 pub mod expression;
+pub mod diagnostic;
+pub mod filelist;
+pub mod cache;
 
 trait VerilogBase {
     fn get_name(&self) -> String;
@@ -1,12 +1,15 @@
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::fmt::format;
-use crate::verilog::port::{PortDir, UndefineWireCollector, VerilogPort};
+use crate::verilog::port::{PortDir, UndefineWireCollector, VerilogPort, VerilogValue};
 use crate::verilog::wire::WireBuilder;
 use std::sync::Arc;
 use crate::verilog::data::{VerilogData, WrapMacro};
+use crate::verilog::diagnostic::DiagnosticCollector;
 use crate::verilog::parameter::Param;
+use crate::verilog::width::Width;
 use crate::verilog::VerilogBase;
+use crate::config::Dialect;
 
 const INST_NAME_LEN: u8 = 20;
 const INST_SIGNAL_LEN: u8 = 25;
@@ -17,6 +20,13 @@ pub struct VerilogModule {
     pub param_list: Vec<Param>,
     pub port_list: Vec<VerilogData<VerilogPort>>,
     pub inst_list: Vec<Arc<RefCell<VerilogData<VerilogModule>>>>,
+
+    /// continuous assign `assign lhs = rhs` found in rtl
+    pub assign_list: Vec<(String, String)>,
+    /// wires declared in rtl
+    pub wire_list: Vec<(String, Width)>,
+    /// marked by `// generate_top: skip`, it is not instantiated in top
+    pub skip: bool,
 }
 impl VerilogModule {
     pub fn new(module_name: String) -> Self {
@@ -82,13 +92,14 @@ impl VerilogModule {
     pub fn fix_inst_name(&mut self, inst_name: &str) {
         self.inst_name = Some(inst_name.into());
     }
-    pub fn set_default_inst_name(&mut self) { self.inst_name = Some(format!("u_{}", self.module_name)) }
+    pub fn set_default_inst_name(&mut self, prefix: &str) { self.inst_name = Some(format!("{}{}", prefix, self.module_name)) }
     
     ///
     /// set all the ports connect to self
     /// 
     pub fn set_default_port_wires(&mut self) {
         for p in self.port_list.iter_mut() {
+            p.set_scope(&self.module_name);
             p.connect_self();
             p.check_health();
         }
@@ -162,18 +173,22 @@ impl VerilogModule {
 
     ///
     /// final check
+    /// return true if there is no finding reported as error
+    /// such as undriven/multi-driver bit or unresolved undefined wire
     ///
-    pub fn final_check(&mut self) {
+    pub fn final_check(&mut self) -> bool {
         if UndefineWireCollector::has_wires() {
-            UndefineWireCollector::solve_func();
-            self.port_list.iter_mut().for_each(|p| p.check_health());
-            self.inst_list.iter_mut().for_each(|inst| {
-                inst.borrow_mut().port_list.iter_mut().for_each(|p| {
-                    p.check_health();
+            if UndefineWireCollector::solve_func() {
+                self.port_list.iter_mut().for_each(|p| p.check_health());
+                self.inst_list.iter_mut().for_each(|inst| {
+                    inst.borrow_mut().port_list.iter_mut().for_each(|p| {
+                        p.check_health();
+                    });
                 });
-            });
+            }
         }
         WireBuilder::check_health();
+        DiagnosticCollector::error_count() == 0
     }
 
     ///
@@ -182,12 +197,14 @@ impl VerilogModule {
     pub fn to_inst_string(&self) -> Vec<String> {
         let mut res = Vec::new();
 
-        if let Some((last_para, params)) = self.param_list.split_last() {
+        // localparam & derived parameters follow the definition of module
+        let overrides = self.param_list.iter().filter(|p| !p.is_derived()).collect::<Vec<_>>();
+        if let Some((last_para, params)) = overrides.split_last() {
             res.push(format!("{} #(", self.module_name));
             for p in params {
-                res.push(format!("    .{:<20}({:<10}),", p.name, p.get_name()));
+                res.extend(p.wrap_line(format!("    .{:<20}({:<10}),", p.name, p.get_name())));
             }
-            res.push(format!("    .{:<20}({:<10})", last_para.name, last_para.get_name()));
+            res.extend(last_para.wrap_line(format!("    .{:<20}({:<10})", last_para.name, last_para.get_name())));
             res.push(format!(") {} (", self.inst_name.as_ref().unwrap()));
         } else {
             res.push(format!(
@@ -209,15 +226,48 @@ impl VerilogModule {
         res
     }
 
-    pub fn to_module_string(&self) -> Vec<String> {
+    ///
+    /// interface instances connected to instance ports
+    /// interfaces passed through top ports are not declared
+    ///
+    fn interface_decls(&self) -> Vec<String> {
+        let mut declared: HashSet<String> = self.port_list
+            .iter()
+            .filter(|p| p.is_interface())
+            .map(|p| p.name.clone())
+            .collect();
+        let mut res = Vec::new();
+        for inst in self.inst_list.iter() {
+            let inst = inst.borrow();
+            for port in inst.port_list.iter().filter(|p| p.is_interface()) {
+                // modport is dropped, `axi_if.master` => `axi_if`
+                let iface = port.interface.as_deref().unwrap_or("interface");
+                let iface = iface.split('.').next().unwrap_or(iface);
+                for sig in port.signals.iter() {
+                    let VerilogValue::Interface(name) = sig else { continue };
+                    if !declared.insert(name.clone()) {
+                        continue;
+                    }
+                    if iface == "interface" {
+                        log::warn!("type of interface {} is unknown, it is not declared", name);
+                        continue;
+                    }
+                    res.push(format!("{} {} ();", iface, name));
+                }
+            }
+        }
+        res
+    }
+
+    pub fn to_module_string(&self, dialect: Dialect) -> Vec<String> {
         let mut res = Vec::new();
         let mut indent = 0;
         let param_string = if let Some((last_para, params)) = self.param_list.split_last() {
             let mut res = vec![String::from("#(")];
             for p in params {
-                res.push(format!("    parameter  {:<20} = {},", p.name, p.get_value()));
+                res.extend(p.wrap_line(format!("    {:<10} {:<20} = {},", p.keyword(), format!("{}{}", p.type_string(), p.name), p.value_string())));
             }
-            res.push(format!("    parameter  {:<20} = {}", last_para.name, last_para.get_value()));
+            res.extend(last_para.wrap_line(format!("    {:<10} {:<20} = {}", last_para.keyword(), format!("{}{}", last_para.type_string(), last_para.name), last_para.value_string())));
             res.push(")".into());
             res.join("\n")
         } else { String::from("") };
@@ -229,10 +279,10 @@ impl VerilogModule {
         // port info
         if let Some((last_port, ports)) = self.port_list.split_last() {
             for port in ports.iter() {
-                res.extend(port.to_port_string(false).into_iter().map(|s| format!("{}{}", " ".repeat(indent), s)));
+                res.extend(port.to_port_string(false, dialect).into_iter().map(|s| format!("{}{}", " ".repeat(indent), s)));
             }
             
-            res.extend(last_port.to_port_string(true).into_iter().map(|s| format!("{}{}", " ".repeat(indent), s)));
+            res.extend(last_port.to_port_string(true, dialect).into_iter().map(|s| format!("{}{}", " ".repeat(indent), s)));
 
             res.push(");\n".to_string());
         }
@@ -241,12 +291,15 @@ impl VerilogModule {
         let s = WireBuilder::traverse_unport_wires()
             .iter()
             .flat_map(|w| {
-                w.to_string()
+                w.to_string(dialect)
             })
             .collect::<Vec<_>>();
         res.extend(s.into_iter().map(|s| format!("{}{}", " ".repeat(indent), s)).collect::<Vec<String>>());
         res.push("\n".to_string());
 
+        // interface instance
+        res.extend(self.interface_decls().into_iter().map(|s| format!("{}{}", " ".repeat(indent), s)));
+
         // port wire connected
         let temp = self.port_list.iter()
             .filter_map(|item| {
@@ -289,7 +342,7 @@ mod test {
 
     #[test]
     fn test_inst_string() {
-        simple_logger::init_with_level(log::Level::Info).unwrap();
+        let _ = simple_logger::init_with_level(log::Level::Info);
         let mut module = VerilogModule::new("test".to_string());
         module.fix_inst_name("u_test_module");
         let mut port1 = VerilogPort::new(PortDir::InPort, "port1", 12.into());
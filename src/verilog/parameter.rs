@@ -1,43 +1,213 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use crate::utils::calculator::StrCalc;
 use crate::verilog::module::VerilogModule;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Param {
     pub name: String,
     pub value: Box<ParamValue>,
+    /// `ifdef guards from outer to inner
+    pub macros: Vec<String>,
+    /// `localparam` can not be overridden by instance
+    pub local: bool,
+    /// defining expression in module, such as `$clog2(DEPTH)`
+    /// it is evaluated again when the parameters it refers to are overridden
+    pub expr: Option<String>,
+    /// declared type, such as `string`, `logic [31:0]` or `type` for type parameter
+    pub data_type: Option<String>,
 }
 
 impl Param {
     pub fn new(name: String, value: usize) -> Param {
-        Param { name, value: Box::new(value.into()) }
+        Param { name, value: Box::new(value.into()), macros: Vec::new(), local: false, expr: None, data_type: None }
     }
 
     pub fn new_with_param(name: String, value: ParamValue) -> Param {
         Param {
             name,
             value: Box::new(value),
+            macros: Vec::new(),
+            local: false,
+            expr: None,
+            data_type: None,
         }
     }
 
+    pub fn with_type(mut self, data_type: Option<String>) -> Self {
+        self.data_type = data_type;
+        self
+    }
+
+    pub fn is_type(&self) -> bool {
+        matches!(*self.value, ParamValue::Type(_))
+    }
+
+    pub fn with_local(mut self, local: bool) -> Self {
+        self.local = local;
+        self
+    }
+
+    pub fn with_expr(mut self, expr: impl Into<String>) -> Self {
+        self.expr = Some(expr.into());
+        self
+    }
+
+    ///
+    /// the value follows the module definition, it is not written as override of instance
+    ///
+    pub fn is_derived(&self) -> bool {
+        self.local || self.expr.is_some()
+    }
+
+    pub fn with_macros(mut self, macros: Vec<String>) -> Self {
+        self.macros = macros;
+        self
+    }
+
+    pub fn get_macro_name(&self) -> String {
+        self.macros.join(", ")
+    }
+
+    ///
+    /// wrap the line of this parameter with its `ifdef guards
+    ///
+    pub fn wrap_line(&self, line: String) -> Vec<String> {
+        let mut res = self.macros.iter().map(|m| format!("`ifdef {}", m)).collect::<Vec<_>>();
+        res.push(line);
+        res.extend(self.macros.iter().rev().map(|m| format!("`endif  // {}", m)));
+        res
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        if self.local { "localparam" } else { "parameter" }
+    }
+
+    ///
+    /// value in declaration, expression is kept
+    ///
+    pub fn value_string(&self) -> String {
+        match (&self.expr, &*self.value) {
+            (Some(e), _) => e.clone(),
+            (None, ParamValue::Literal(s) | ParamValue::Type(s)) => s.clone(),
+            _ => self.get_value().to_string(),
+        }
+    }
+
+    ///
+    /// declared type followed by a space, empty for implicit type
+    ///
+    pub fn type_string(&self) -> String {
+        self.data_type.as_ref().map(|t| format!("{} ", t)).unwrap_or_default()
+    }
+
+    ///
+    /// integer value, sized number is converted, 0 for string, real & type
+    ///
     pub fn get_value(&self) -> usize {
         match *self.value {
             ParamValue::Value(x) => {x}
             ParamValue::Param(ref p) => {p.get_value()}
+            ParamValue::Literal(ref s) => Self::eval_expr(s, &Vec::new()).unwrap_or(0),
+            ParamValue::Type(_) => 0,
         }
     }
 
     pub fn get_name(&self) -> String {
+        if let Some(expr) = &self.expr {
+            return expr.clone();
+        }
         match *self.value {
             ParamValue::Value(x) => {format!("{}", x)}
             ParamValue::Param(ref p) => {p.name.clone()}
+            ParamValue::Literal(ref s) | ParamValue::Type(ref s) => s.clone(),
+        }
+    }
+
+    ///
+    /// value of expression with `params`, such as `$clog2(DEPTH) + 1`
+    /// None if it refers to unknown name or can not be calculated
+    ///
+    pub fn eval_expr(text: &str, params: &Vec<Param>) -> Option<usize> {
+        let number_re = Regex::new(r"(\d*)\s*'[sS]?([bodhBODH])\s*([0-9a-fA-F_]+)").unwrap();
+        let name_re = Regex::new(r"[a-zA-Z_]\w*").unwrap();
+        let mut text = text.trim().to_string();
+        let mut unknown = false;
+        text = number_re.replace_all(&text, |c: &Captures| {
+            let base = match c[2].to_ascii_lowercase().as_str() {
+                "b" => 2,
+                "o" => 8,
+                "h" => 16,
+                _ => 10,
+            };
+            u128::from_str_radix(&c[3].replace('_', ""), base).map_or_else(|_| {
+                unknown = true;
+                c[0].to_string()
+            }, |v| v.to_string())
+        }).to_string();
+        // `$clog2` is evaluated from inner to outer
+        while let Some(start) = text.find("$clog2") {
+            let open = start + text[start..].find('(')?;
+            let mut depth = 0;
+            let close = open + text[open..].char_indices().find(|(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })?.0;
+            let value = Self::eval_expr(&text[open + 1..close], params)?;
+            let log = if value <= 1 { 0 } else { (usize::BITS - (value - 1).leading_zeros()) as usize };
+            text.replace_range(start..=close, &log.to_string());
+        }
+        // later declaration shadows the former one
+        text = name_re.replace_all(&text, |c: &Captures| {
+            match params.iter().rev().find(|p| p.name == c[0]) {
+                Some(p) => p.get_value().to_string(),
+                None => {
+                    unknown = true;
+                    c[0].to_string()
+                }
+            }
+        }).to_string();
+        if unknown {
+            return None;
         }
+        text.calculate().ok()
     }
 
+    ///
+    /// parameters of instance, `overrides` replace the defaults
+    /// the derived parameters which are not overridden are evaluated again in declaration order
+    ///
+    pub fn override_params(defaults: &[Param], overrides: &[Param]) -> Vec<Param> {
+        let mut res: Vec<Param> = Vec::with_capacity(defaults.len());
+        for p in defaults.iter() {
+            let over = overrides.iter().find(|o| o.name == p.name && !o.is_derived() && !p.local);
+            let mut param = over.unwrap_or(p).clone();
+            if over.is_none()
+                && let Some(value) = p.expr.as_deref().and_then(|e| Self::eval_expr(e, &res))
+            {
+                param.value = Box::new(value.into());
+            }
+            res.push(param);
+        }
+        for o in overrides.iter().filter(|o| !defaults.iter().any(|p| p.name == o.name)) {
+            res.push(o.clone());
+        }
+        res
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ParamValue {
     Value(usize),
     Param(Param),
+    /// string, sized number or real, it is written verbatim, such as `"rom.hex"` or `32'hDEAD_BEEF`
+    Literal(String),
+    /// data type of `parameter type`, such as `logic [7:0]`
+    Type(String),
 }
 
 impl From<usize> for ParamValue {
@@ -59,6 +229,24 @@ impl ParamValue {
                 return item.clone().into();
             }
         }
-        ParamValue::Value(0)
+        if let Some(v) = Self::from_literal(&name) {
+            return v;
+        }
+        log::warn!("parameter value {} is neither parameter of top nor literal, it is kept verbatim", name);
+        ParamValue::Literal(name)
+    }
+
+    ///
+    /// integer, string, sized number & real, None for expression
+    ///
+    pub fn from_literal(text: &str) -> Option<ParamValue> {
+        let text = text.trim();
+        if let Ok(v) = text.replace('_', "").parse::<usize>() {
+            return Some(ParamValue::Value(v));
+        }
+        let literal_re = Regex::new(
+            r#"^("([^"\\]|\\.)*"|\d*\s*'[sS]?[bodhBODH]\s*[0-9a-fA-FxzXZ_?]+|'[01xzXZ]|\d[\d_]*(\.[\d_]+)?([eE][+-]?\d+)?)$"#
+        ).unwrap();
+        literal_re.is_match(text).then(|| ParamValue::Literal(text.into()))
     }
 }
\ No newline at end of file
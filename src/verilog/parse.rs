@@ -1,15 +1,27 @@
 use crate::verilog::module::VerilogModule;
 use crate::verilog::port::{PortDir, VerilogPort};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
+use std::sync::{Arc, LazyLock};
 use std::{usize};
 use std::io::Write;
 use std::path::PathBuf;
-use sv_parser::{ConstantExpression, Define, PortDeclaration, PortDirection, RefNode, SyntaxTree, parse_sv, unwrap_node};
+use regex::Regex;
+use sv_parser::{preprocess, ConstantExpression, DataType, DataTypeOrVoid, Define, DefineText, Description, InterfacePortDeclaration, Locate, PackedDimension, PortDeclaration, PortDirection, RefNode, StructUnion, SyntaxTree, TypeDeclaration, UnpackedDimension, parse_sv, unwrap_node};
+use crate::config::Config;
+use crate::error::{Error, Result};
 use crate::verilog::data::{VerilogData, WrapMacro};
-use crate::verilog::parameter::Param;
+use crate::verilog::parameter::{Param, ParamValue};
 use crate::verilog::width::Width;
 use crate::verilog::width::Width::RawWidth;
+use crate::verilog::diagnostic::{Diagnostic, DiagnosticCollector, DiagnosticKind};
+use crate::verilog::cache::Digest;
+
+/// comment line which is a commented out declaration, such as `// output logic [7:0] rdata,`
+static COMMENTED_DECL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:input|output|inout|wire|reg|logic|parameter|localparam)\b(?:[^,;]*[,;]|.*\]\s*\w+)\s*$").unwrap()
+});
 
 pub struct VerilogParser<'a> {
     file: &'a PathBuf,
@@ -18,6 +30,62 @@ pub struct VerilogParser<'a> {
 
     parse_res: Option<SyntaxTree>,
     module_info: Vec<VerilogModule>,
+    // (index of parent module in `module_info`, instance)
+    insts: Vec<(usize, ParsedInst)>,
+    // definitions of instantiated modules in other files
+    library: Vec<VerilogModule>,
+    // interfaces declared in this file
+    interfaces: HashSet<String>,
+    // `ifdef guards of every line in this file & offset of line start
+    guards: Vec<Vec<Guard>>,
+    line_starts: Vec<usize>,
+    // text of this file, used to collect the comments of ports
+    source: String,
+    // macros which are not defined, they are replaced by `UNRESOLVED_PREFIX` + name
+    unresolved: HashSet<String>,
+    // width of types in packages as `pkg::type` & types declared out of module
+    types: HashMap<String, usize>,
+    // imported items of packages, such as `pkg::*` or `pkg::req_t`
+    imports: Vec<String>,
+    // connections of instances which can not be resolved, such as `u_fifo.din: a & b`
+    dropped_conns: Vec<String>,
+}
+
+const UNRESOLVED_PREFIX: &str = "GT_UNRESOLVED_";
+
+///
+/// condition of `ifdef block, `ifndef & `else are negative
+///
+#[derive(Debug, Clone, PartialEq)]
+enum Guard {
+    Defined(String),
+    Undefined(String),
+}
+
+impl Guard {
+    fn negate(&self) -> Self {
+        match self {
+            Guard::Defined(s) => Guard::Undefined(s.clone()),
+            Guard::Undefined(s) => Guard::Defined(s.clone()),
+        }
+    }
+}
+
+///
+/// instance found in rtl
+/// ordered & `.*` connections need the definition of instantiated module,
+/// so they are resolved after all the modules are extracted
+///
+#[derive(Debug, Default, Clone)]
+struct ParsedInst {
+    module_name: String,
+    inst_name: String,
+    // (parameter name, expression), name is None for ordered assignment
+    params: Vec<(Option<String>, String)>,
+    // (port name, expression), name is None for ordered connection
+    // and expression is None for `.port()`
+    conns: Vec<(Option<String>, Option<String>)>,
+    wildcard: bool,
 }
 
 impl<'a> VerilogParser<'a> {
@@ -29,11 +97,54 @@ impl<'a> VerilogParser<'a> {
             
             parse_res: None,
             module_info: Vec::new(),
+            insts: Vec::new(),
+            library: Vec::new(),
+            interfaces: HashSet::new(),
+            guards: Vec::new(),
+            line_starts: Vec::new(),
+            source: String::new(),
+            unresolved: HashSet::new(),
+            types: HashMap::new(),
+            imports: Vec::new(),
+            dropped_conns: Vec::new(),
         }
     }
 
-    pub fn add_define(mut self, name: impl Into<String>) -> Self {
-        self.defines.insert(name.into(), None);
+    pub fn add_define(mut self, name: impl Into<String>, value: Option<String>) -> Self {
+        let name = name.into();
+        let define = value.map(|v| {
+            Define::new(name.clone(), Vec::new(), Some(DefineText::new(v, None)))
+        });
+        self.defines.insert(name, define);
+        self
+    }
+
+    ///
+    /// modules used to resolve ports of instances, whose definition is not in this file
+    ///
+    pub fn with_library(mut self, modules: Vec<VerilogModule>) -> Self {
+        self.library = modules;
+        self
+    }
+
+    ///
+    /// types of packages in other files, such as `my_pkg::req_t`
+    ///
+    pub fn with_packages(mut self, types: HashMap<String, usize>) -> Self {
+        self.types.extend(types);
+        self
+    }
+
+    ///
+    /// `define macros in headers are visible to this file, without `include
+    ///
+    pub fn add_headers(mut self, headers: Vec<PathBuf>) -> Self {
+        for header in headers.iter() {
+            match preprocess(header, &self.defines, &self.includes, false, false) {
+                Ok((_, defines)) => self.defines.extend(defines),
+                Err(e) => log::warn!("can not preprocess header {}: {}, skip it", header.display(), e),
+            }
+        }
         self
     }
 
@@ -42,38 +153,176 @@ impl<'a> VerilogParser<'a> {
         self
     }
 
-    pub fn parse(mut self) -> Self {
-        let res = parse_sv(&self.file, &self.defines, &self.includes, false, false);
-        match res {
-            Ok(t) => {
-                log::info!("file {} parsed successfully", self.file.display());
-                self.parse_res = Some(t.0)
+    ///
+    /// add include dirs & defines from project config
+    ///
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self = self.add_includes(config.include_dirs.clone());
+        for (name, value) in config.define_pairs() {
+            self = self.add_define(name, value);
+        }
+        self.add_headers(config.headers.clone())
+    }
+
+    ///
+    /// hash of the file content, included files, defines & include dirs
+    /// the cached interfaces of this file are valid while it is not changed
+    ///
+    pub fn cache_key(&self) -> Option<String> {
+        let text = std::fs::read_to_string(self.file).ok()?;
+        let mut digest = Digest::new();
+        digest.update(self.file.to_string_lossy().as_bytes()).update(&text);
+        self.hash_includes(&text, &mut digest, &mut HashSet::new());
+        let mut defines = self.defines.iter().map(|(n, d)| format!("{} {:?}", n, d)).collect::<Vec<_>>();
+        defines.sort();
+        defines.iter().for_each(|d| { digest.update(d); });
+        self.includes.iter().for_each(|p| { digest.update(p.to_string_lossy().as_bytes()); });
+        Some(digest.finish())
+    }
+
+    fn hash_includes(&self, text: &str, digest: &mut Digest, visited: &mut HashSet<PathBuf>) {
+        let re = Regex::new(r#"`include\s+"([^"]+)""#).unwrap();
+        for c in re.captures_iter(text) {
+            let found = self.file
+                .parent()
+                .into_iter()
+                .chain(self.includes.iter().map(|p| p.as_path()))
+                .map(|d| d.join(&c[1]))
+                .find(|p| p.is_file());
+            let Some(path) = found else {
+                digest.update(&c[1]);
+                continue;
+            };
+            if !visited.insert(path.clone()) {
+                continue;
             }
-            Err(e) => {
-                panic!("file {} parse error: {:?}", self.file.display(), e)
+            if let Ok(inc) = std::fs::read_to_string(&path) {
+                digest.update(&inc);
+                self.hash_includes(&inc, digest, visited);
             }
         }
-        self
     }
+
+    pub fn parse(mut self) -> Result<Self> {
+        loop {
+            match parse_sv(self.file, &self.defines, &self.includes, false, false) {
+                Ok(t) => {
+                    log::info!("file {} parsed successfully", self.file.display());
+                    self.parse_res = Some(t.0);
+                    return Ok(self);
+                }
+                // undefined macro is replaced by a placeholder, ports using it are unresolved
+                Err(e) => match Self::undefined_macro(&e) {
+                    Some(name) if !self.unresolved.contains(&name) => {
+                        log::warn!("macro `{} is not defined in {}", name, self.file.display());
+                        let text = DefineText::new(format!("{}{}", UNRESOLVED_PREFIX, name), None);
+                        self.defines.insert(name.clone(), Some(Define::new(name.clone(), Vec::new(), Some(text))));
+                        self.unresolved.insert(name);
+                    }
+                    _ => return Err(Self::parse_error(self.file, &e)),
+                },
+            }
+        }
+    }
+
+    ///
+    /// locate the error of sv-parser in the source file
+    ///
+    fn parse_error(file: &PathBuf, e: &sv_parser::Error) -> Error {
+        match e {
+            sv_parser::Error::Parse(Some((path, offset)))
+            | sv_parser::Error::Preprocess(Some((path, offset))) => match std::fs::read_to_string(path) {
+                Ok(text) => Error::syntax(path, &text, *offset),
+                Err(_) => Error::Parse { file: path.clone(), msg: format!("error at byte {}", offset) },
+            },
+            sv_parser::Error::Include { source } => Self::parse_error(file, source),
+            _ => Error::Parse { file: file.clone(), msg: e.to_string() },
+        }
+    }
+
+    fn undefined_macro(e: &sv_parser::Error) -> Option<String> {
+        match e {
+            sv_parser::Error::DefineNotFound(name) => Some(name.clone()),
+            sv_parser::Error::Include { source } => Self::undefined_macro(source),
+            _ => None,
+        }
+    }
+
+    ///
+    /// flag the ports whose width uses undefined macro
+    /// the placeholder is shown as the macro usage, such as `` `WIDTH-1-0+1 ``
+    ///
+    fn mark_unresolved(&self, module: &mut VerilogModule) {
+        if self.unresolved.is_empty() {
+            return;
+        }
+        let re = Regex::new(&format!(r"{}(\w+)", UNRESOLVED_PREFIX)).unwrap();
+        for port in module.port_list.iter_mut() {
+            let text = format!("{} {:?}", port.width, port.unpacked);
+            let macros = re.captures_iter(&text).map(|c| format!("`{}", &c[1])).collect::<Vec<_>>();
+            if macros.is_empty() {
+                continue;
+            }
+            port.unresolved = true;
+            if port.width.is_literal() {
+                port.width = re.replace_all(&port.width.to_string(), "`$1").to_string().into();
+            }
+            let msg = format!("width of port {}.{} uses undefined macro {}", module.module_name, port.name, macros.join(", "));
+            log::error!("{}", msg);
+            DiagnosticCollector::report(Diagnostic::new(DiagnosticKind::UnresolvedWidth, &port.name, msg));
+        }
+    }
+
+    ///
+    /// port typed by `parameter type` is not visible outside, only the resolved width is kept
+    ///
+    fn drop_param_types(module: &mut VerilogModule, params: &[Param]) {
+        for port in module.port_list.iter_mut() {
+            if params.iter().any(|p| p.is_type() && port.data_type.as_ref() == Some(&p.name)) {
+                port.data_type = None;
+            }
+        }
+    }
+
     pub fn solve(mut self) -> Self {
+        if let Ok(text) = std::fs::read_to_string(self.file) {
+            self.scan_guards(&text);
+            self.source = text;
+        }
         self.extract_module();
+        self.merge_guarded_ports();
+        self.resolve_insts();
 
         self
     }
 
+    pub fn file(&self) -> &'a PathBuf {
+        self.file
+    }
+
+    ///
+    /// connections of instances which are not in `inst_list`, they are only logged by `solve`
+    ///
+    pub fn dropped_conns(&self) -> &[String] {
+        &self.dropped_conns
+    }
+
     pub fn get_module_info(self) -> Vec<VerilogModule> {
         self.module_info
     }
 
     pub fn extract_module(&mut self) {
         log::debug!("start extract module");
+        self.extract_types();
 
-        let tree = self.parse_res.as_ref().unwrap();
+        let Some(tree) = self.parse_res.as_ref() else {
+            log::error!("file {} is not parsed", self.file.display());
+            return;
+        };
 
         #[cfg(debug_assertions)]
-        {
-            let mut file = File::create("dump-tree.txt").unwrap();
-            writeln!(file, "{}", tree).unwrap();
+        if let Ok(mut file) = File::create("dump-tree.txt") {
+            let _ = writeln!(file, "{}", tree);
         }
 
         for node in tree {
@@ -90,14 +339,26 @@ impl<'a> VerilogParser<'a> {
 
                     // add parameter list
                     let params = self.extract_params(RefNode::from(module_node));
+                    let mut types = HashMap::new();
+                    self.extract_typedefs(RefNode::from(module_node), &params, &mut types);
 
                     //add port
-                    module.add_ports(self.extract_ports(RefNode::from(module_node), &params));
+                    module.add_ports(self.extract_ports(RefNode::from(module_node), &params, &types));
+                    self.mark_unresolved(&mut module);
+                    module.skip = self.has_skip_pragma(RefNode::from(module_node));
+                    Self::drop_param_types(&mut module, &params);
 
                     // add parameter list
                     module.add_param_list(params);
 
-                    // TODO add inst
+                    // add inst
+                    for inst in self.extract_insts(RefNode::from(module_node)) {
+                        self.insts.push((self.module_info.len(), inst));
+                    }
+
+                    // add assign & wire
+                    module.assign_list = self.extract_assigns(RefNode::from(module_node));
+                    module.wire_list = self.extract_wires(RefNode::from(module_node), &module.param_list);
 
                     // add module
                     self.module_info.push(module);
@@ -114,107 +375,698 @@ impl<'a> VerilogParser<'a> {
 
                     // add parameter list
                     let params = self.extract_ansi_params(RefNode::from(module_node));
+                    let mut types = HashMap::new();
+                    self.extract_typedefs(RefNode::from(module_node), &params, &mut types);
 
                     // add ports
-                    module.add_ports(self.extract_ansi_ports(RefNode::from(module_node), &params));
+                    module.add_ports(self.extract_ansi_ports(RefNode::from(module_node), &params, &types));
+                    self.mark_unresolved(&mut module);
+                    module.skip = self.has_skip_pragma(RefNode::from(module_node));
+                    Self::drop_param_types(&mut module, &params);
 
                     // add parameter list
                     module.add_param_list(params);
 
-                    // TODO add inst
+                    // add inst
+                    for inst in self.extract_insts(RefNode::from(module_node)) {
+                        self.insts.push((self.module_info.len(), inst));
+                    }
+
+                    // add assign & wire
+                    module.assign_list = self.extract_assigns(RefNode::from(module_node));
+                    module.wire_list = self.extract_wires(RefNode::from(module_node), &module.param_list);
 
                     // add module
                     self.module_info.push(module);
                 }
+                RefNode::InterfaceDeclaration(iface_node) => {
+                    if let Some(name) = unwrap_node!(iface_node, InterfaceIdentifier)
+                        .and_then(|id| self.get_identifier_string(id))
+                    {
+                        self.interfaces.insert(name);
+                    }
+                }
                 _ => {}
             }
         }
         log::debug!("end extract module");
     }
 
-    fn extract_ansi_params(&self, module_node: RefNode) -> Vec<Param> {
-        log::debug!("start extract ansi parameters");
-        let mut params = Vec::new();
+    ///
+    /// record `ifdef guards of every line, directives in comments are ignored
+    ///
+    fn scan_guards(&mut self, text: &str) {
+        let directive_re = Regex::new(r"`(ifdef|ifndef|elsif|else|endif)\b\s*(\w*)").unwrap();
+        // conditions of every nested level
+        let mut stack: Vec<Vec<Guard>> = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            self.line_starts.push(offset);
+            self.guards.push(stack.iter().flatten().cloned().collect());
+            offset += line.len();
+            let code = line.split("//").next().unwrap_or(line);
+            for c in directive_re.captures_iter(code) {
+                let name = c[2].to_string();
+                match &c[1] {
+                    "ifdef" => stack.push(vec![Guard::Defined(name)]),
+                    "ifndef" => stack.push(vec![Guard::Undefined(name)]),
+                    "elsif" => if let Some(level) = stack.last_mut() {
+                        if let Some(last) = level.pop() {
+                            level.push(last.negate());
+                        }
+                        level.push(Guard::Defined(name));
+                    },
+                    "else" => if let Some(last) = stack.last_mut().and_then(|l| l.pop()) {
+                        stack.last_mut().unwrap().push(last.negate());
+                    },
+                    _ => { stack.pop(); }
+                }
+            }
+        }
+    }
 
-        for para_node in unwrap_node!(module_node, ParameterPortListDeclaration)
-            .into_iter()
+    ///
+    /// ports in `ifdef blocks of undefined macros are removed by preprocessor
+    /// the file is parsed again with these macros defined, and the guarded ports are merged
+    ///
+    fn merge_guarded_ports(&mut self) {
+        let macros = self.guards
+            .iter()
             .flatten()
-        {
-            if let RefNode::ParameterDeclaration(para) = para_node {
-                let token = if let Some(id) = unwrap_node!(para, ParameterIdentifier) {
-                    self.get_identifier_string(id)
-                } else { None };
-                let value = if let Some(t) = unwrap_node!(para, ConstantExpression) {
-                    self.get_literal_string(t)
-                } else { None };
-                if token.is_some() && value.is_some() {
-                    log::debug!("Find parameter token is {:?}, value is {:?}", token, value);
-                    params.push(Param::new(token.unwrap(), value.unwrap().width()));
-                } else {
-                    log::debug!("Error find parameter token is {:?}, value is {:?}", token, value);
+            .filter_map(|g| match g {
+                Guard::Defined(m) if !self.defines.contains_key(m) => Some(m.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        if macros.is_empty() {
+            return;
+        }
+        let mut branch = VerilogParser::new(self.file).add_includes(self.includes.clone());
+        branch.defines = self.defines.clone();
+        for m in macros.iter() {
+            branch = branch.add_define(m, None);
+        }
+        let branch = match branch.parse() {
+            Ok(p) => p,
+            Err(e) => {
+                log::warn!("{}, ports guarded by `ifdef {:?} are dropped", e, macros);
+                return;
+            }
+        };
+        let modules = branch.with_packages(self.types.clone()).solve().get_module_info();
+        for m in modules {
+            let Some(module) = self.module_info.iter_mut().find(|x| x.module_name == m.module_name) else { continue };
+            // guarded port is inserted after the port before it
+            let mut pos = 0;
+            for p in m.port_list {
+                match module.port_list.iter().position(|x| x.name == p.name) {
+                    Some(idx) => pos = idx + 1,
+                    None if !p.macros().is_empty() => {
+                        log::debug!("add port {} guarded by `ifdef {}", p.name, p.get_macro_name());
+                        module.port_list.insert(pos, p);
+                        pos += 1;
+                    }
+                    None => {}
                 }
             }
         }
+    }
+
+    ///
+    /// `ifdef macros around the node, from inner to outer as `wrap_macro_with` expects
+    /// `ifndef & `else can not be wrapped, they are dropped with warning
+    ///
+    fn macros_of(&self, node: RefNode, name: &str) -> Vec<String> {
+        let Some(tree) = self.parse_res.as_ref() else { return Vec::new() };
+        let Some(locate) = node.into_iter().find_map(|n| match n {
+            RefNode::Locate(l) => Some(*l),
+            _ => None,
+        }) else { return Vec::new() };
+        let Some((path, offset)) = tree.get_origin(&locate) else { return Vec::new() };
+        if path != self.file {
+            return Vec::new();
+        }
+        let line = self.line_starts.partition_point(|&s| s <= offset).saturating_sub(1);
+        let mut res = Vec::new();
+        for guard in self.guards.get(line).into_iter().flatten() {
+            match guard {
+                Guard::Defined(m) => res.push(m.clone()),
+                Guard::Undefined(m) => log::warn!("{} is guarded by `ifndef {} or `else, the guard is dropped", name, m),
+            }
+        }
+        res.reverse();
+        res
+    }
+
+    ///
+    /// comments before & after the declaration node, used as info of the port
+    /// the preceding comment lines are stopped by blank line or code
+    ///
+    fn comment_of(&self, node: RefNode) -> String {
+        let Some((start, end)) = self.span_of(node) else { return String::new() };
+        let mut res = self.preceding_comments(start);
+
+        let rest = &self.source[end..];
+        let rest = rest.split('\n').next().unwrap_or_default();
+        let comment = rest.find("//").or_else(|| rest.find("/*"));
+        // the comment belongs to the next declaration if there is code before it
+        if let Some(i) = comment.filter(|&i| rest[..i].trim().chars().all(|c| ",;)".contains(c))) {
+            res.push(Self::strip_comment(&rest[i..]));
+        }
+        res.retain(|s| !s.is_empty());
+        res.join(" ")
+    }
+
+    ///
+    /// byte span of the node in this file, whitespace & comments inside the node are skipped
+    ///
+    fn span_of(&self, node: RefNode) -> Option<(usize, usize)> {
+        let tree = self.parse_res.as_ref()?;
+        let mut spaces = HashSet::new();
+        let mut span: Option<(usize, usize)> = None;
+        for n in node.into_iter() {
+            match n {
+                RefNode::WhiteSpace(ws) => {
+                    for x in RefNode::from(ws).into_iter() {
+                        if let RefNode::Locate(l) = x {
+                            spaces.insert(l.offset);
+                        }
+                    }
+                }
+                RefNode::Locate(l) if !spaces.contains(&l.offset) => {
+                    let Some((path, offset)) = tree.get_origin(l) else { continue };
+                    if path != self.file {
+                        continue;
+                    }
+                    span = Some(span.map_or((offset, offset + l.len), |(s, _)| (s, offset + l.len)));
+                }
+                _ => {}
+            }
+        }
+        span.filter(|&(_, end)| end <= self.source.len())
+    }
+
+    ///
+    /// comment lines just before the line of `start`, stopped by blank line or code
+    /// commented out declaration is code, the comments before it are not collected
+    ///
+    fn preceding_comments(&self, start: usize) -> Vec<String> {
+        let first = self.line_starts.partition_point(|&s| s <= start).saturating_sub(1);
+        let mut lines = self.source[..self.line_starts[first]].lines().rev();
+        let mut preceding = Vec::new();
+        while let Some(line) = lines.next() {
+            let line = line.trim();
+            if line.starts_with("//") && COMMENTED_DECL_RE.is_match(&Self::strip_comment(line)) {
+                break;
+            } else if line.starts_with("//") {
+                preceding.push(line);
+            } else if line.ends_with("*/") {
+                // collect the whole block comment
+                let mut block = vec![line];
+                let mut complete = line.contains("/*");
+                while !complete {
+                    let Some(l) = lines.next() else { break };
+                    block.push(l.trim());
+                    complete = l.contains("/*");
+                }
+                if !complete || !block.last().unwrap().starts_with("/*") {
+                    break;
+                }
+                preceding.extend(block);
+            } else {
+                break;
+            }
+        }
+        preceding.into_iter().rev().map(Self::strip_comment).collect()
+    }
+
+    ///
+    /// module is marked by `// generate_top: skip` in the comment before it or on the `module` line
+    ///
+    fn has_skip_pragma(&self, node: RefNode) -> bool {
+        let re = Regex::new(r"generate_top\s*:\s*skip\b").unwrap();
+        let Some((start, _)) = self.span_of(node) else { return false };
+        let line = self.line_starts.partition_point(|&s| s <= start).saturating_sub(1);
+        let header = self.source[self.line_starts[line]..].lines().next().unwrap_or_default();
+        let header_comment = header.find("//").map_or("", |i| &header[i..]);
+        re.is_match(header_comment) || self.preceding_comments(start).iter().any(|c| re.is_match(c))
+    }
+
+    ///
+    /// text of a comment line without comment marks & decorations such as `// ----`
+    ///
+    fn strip_comment(line: &str) -> String {
+        let line = line.trim();
+        let line = line.strip_prefix("//").unwrap_or(line);
+        let line = line.split("*/").next().unwrap_or(line);
+        let line = line.trim_start_matches("/*");
+        line.trim_matches(|c: char| c.is_whitespace() || "-=*#/".contains(c)).to_string()
+    }
+
+    fn extract_insts(&self, module_node: RefNode) -> Vec<ParsedInst> {
+        log::debug!("start extract insts");
+        let mut res = Vec::new();
+        for node in module_node.into_iter() {
+            let RefNode::ModuleInstantiation(inst_node) = node else { continue };
+            let module_name = unwrap_node!(inst_node, ModuleIdentifier)
+                .and_then(|id| self.get_identifier_string(id))
+                .unwrap_or_default();
+
+            // parameter overrides are shared by all the instances in one statement
+            let mut params = Vec::new();
+            for item in RefNode::from(inst_node).into_iter() {
+                match item {
+                    RefNode::NamedParameterAssignment(p) => {
+                        let name = self.get_identifier_string(RefNode::from(&p.nodes.1));
+                        let value = p.nodes.2.nodes.1.as_ref().and_then(|e| self.get_node_text(RefNode::from(e)));
+                        if let (Some(name), Some(value)) = (name, value) {
+                            params.push((Some(name), value));
+                        }
+                    }
+                    RefNode::OrderedParameterAssignment(p) => {
+                        if let Some(value) = self.get_node_text(RefNode::from(&p.nodes.0)) {
+                            params.push((None, value));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            for item in RefNode::from(inst_node).into_iter() {
+                let RefNode::HierarchicalInstance(hier) = item else { continue };
+                let inst_name = unwrap_node!(hier, InstanceIdentifier)
+                    .and_then(|id| self.get_identifier_string(id))
+                    .unwrap_or_default();
+                let mut inst = ParsedInst {
+                    module_name: module_name.clone(),
+                    inst_name,
+                    params: params.clone(),
+                    ..Default::default()
+                };
+                for conn in RefNode::from(hier).into_iter() {
+                    match conn {
+                        RefNode::NamedPortConnectionIdentifier(c) => {
+                            let name = self.get_identifier_string(RefNode::from(&c.nodes.2));
+                            let expr = match &c.nodes.3 {
+                                // `.name` is connected to the wire with the same name
+                                None => name.clone(),
+                                Some(paren) => paren.nodes.1.as_ref().and_then(|e| self.get_node_text(RefNode::from(e))),
+                            };
+                            inst.conns.push((name, expr));
+                        }
+                        RefNode::NamedPortConnectionAsterisk(_) => inst.wildcard = true,
+                        RefNode::OrderedPortConnection(c) => {
+                            let expr = c.nodes.1.as_ref().and_then(|e| self.get_node_text(RefNode::from(e)));
+                            inst.conns.push((None, expr));
+                        }
+                        _ => {}
+                    }
+                }
+                log::debug!("find inst {} of module {}", inst.inst_name, inst.module_name);
+                res.push(inst);
+            }
+        }
+        res
+    }
+
+    fn extract_assigns(&self, module_node: RefNode) -> Vec<(String, String)> {
+        let mut res = Vec::new();
+        for node in module_node.into_iter() {
+            if let RefNode::NetAssignment(assign) = node {
+                let lhs = self.get_node_text(RefNode::from(&assign.nodes.0));
+                let rhs = self.get_node_text(RefNode::from(&assign.nodes.2));
+                if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+                    res.push((lhs, rhs));
+                }
+            }
+        }
+        res
+    }
+
+    fn extract_wires(&self, module_node: RefNode, params: &Vec<Param>) -> Vec<(String, Width)> {
+        let mut res = Vec::new();
+        for node in module_node.into_iter() {
+            if let RefNode::NetDeclaration(decl) = node {
+                let width = self.get_port_width(RefNode::from(decl)).width_from(params);
+                for item in RefNode::from(decl).into_iter() {
+                    if let RefNode::NetIdentifier(id) = item
+                        && let Some(name) = self.get_identifier_string(RefNode::from(id))
+                    {
+                        res.push((name, width.clone()));
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    ///
+    /// build instances with the definitions found in this file
+    ///
+    fn resolve_insts(&mut self) {
+        let insts = std::mem::take(&mut self.insts);
+        // `axi_if u_bus ();` is an interface instance, not a module instance
+        let mut interfaces = self.interfaces.clone();
+        interfaces.extend(self.module_info
+            .iter()
+            .chain(self.library.iter())
+            .flat_map(|m| m.port_list.iter())
+            .filter_map(|p| p.interface.as_deref())
+            .map(|s| s.split('.').next().unwrap_or(s).to_string()));
+        for (idx, inst) in insts {
+            if interfaces.contains(&inst.module_name) {
+                log::debug!("skip interface instance {}", inst.inst_name);
+                continue;
+            }
+            let mut dropped = Vec::new();
+            let inst_module = self.build_inst(&inst, &self.module_info[idx].param_list, &mut dropped);
+            for d in dropped.iter() {
+                log::error!("can not resolve connection {} in file {}, it is dropped", d, self.file.display());
+            }
+            self.dropped_conns.extend(dropped);
+            self.module_info[idx].add_inst_module(Arc::new(RefCell::new(inst_module.wrap_raw())));
+        }
+    }
+
+    fn build_inst(&self, inst: &ParsedInst, parent_params: &Vec<Param>, dropped: &mut Vec<String>) -> VerilogModule {
+        let def = self.module_info
+            .iter()
+            .chain(self.library.iter())
+            .find(|m| m.module_name == inst.module_name);
+        if def.is_none() {
+            log::debug!("module {} is not defined in file {}", inst.module_name, self.file.display());
+        }
+        let mut module = VerilogModule::new(inst.module_name.clone());
+        module.fix_inst_name(&inst.inst_name);
+
+        // parameter overrides
+        let mut params: Vec<Param> = Vec::new();
+        for (idx, (name, text)) in inst.params.iter().enumerate() {
+            // localparam is not in the ordered list
+            let name = name.clone().or_else(|| {
+                def.and_then(|d| d.param_list.iter().filter(|p| !p.local).nth(idx)).map(|p| p.name.clone())
+            });
+            let Some(name) = name else {
+                log::warn!("can not resolve ordered parameter #{} of inst {}", idx, inst.inst_name);
+                continue;
+            };
+            // type parameter is overridden by data type, such as `logic [15:0]`
+            let is_type = def
+                .and_then(|d| d.param_list.iter().find(|p| p.name == name))
+                .is_some_and(|p| p.is_type());
+            if is_type {
+                params.push(Param::new_with_param(name, ParamValue::Type(text.clone())).with_type(Some("type".into())));
+                continue;
+            }
+            match Self::eval_param(text, parent_params) {
+                Some(v) => params.push(Param::new_with_param(name, v)),
+                None => log::warn!("can not evaluate parameter {} = {} of inst {}", name, text, inst.inst_name),
+            }
+        }
+        // port width is calculated by overrides, parameters derived from them are evaluated again
+        let width_params = match def {
+            Some(d) => Param::override_params(&d.param_list, &params),
+            None => params.clone(),
+        };
+
+        let mut connected = HashSet::new();
+        for (idx, (name, expr)) in inst.conns.iter().enumerate() {
+            let def_port = match name {
+                Some(n) => def.and_then(|d| d.port_list.iter().find(|p| &p.name == n)),
+                None => def.and_then(|d| d.port_list.get(idx)),
+            };
+            let Some(name) = name.clone().or_else(|| def_port.map(|p| p.name.clone())) else {
+                log::warn!("can not resolve ordered port #{} of inst {}", idx, inst.inst_name);
+                continue;
+            };
+            connected.insert(name.clone());
+            let (port, item) = Self::build_inst_port(&name, def_port.map(|p| &**p), expr.as_deref(), &width_params, parent_params);
+            if let Some(item) = item {
+                dropped.push(format!("{}.{}: `{}`", inst.inst_name, name, item));
+            }
+            // guards of the definition are kept
+            module.add_port_inst(match def_port {
+                Some(p) => port.wrap_macro_as(p),
+                None => port.wrap_raw(),
+            });
+        }
+        if inst.wildcard {
+            match def {
+                Some(d) => {
+                    for p in d.port_list.iter().filter(|p| !connected.contains(&p.name)) {
+                        let (port, _) = Self::build_inst_port(&p.name, Some(&**p), Some(&p.name), &width_params, parent_params);
+                        module.add_port_inst(port.wrap_macro_as(p));
+                    }
+                }
+                None => log::warn!("can not resolve `.*` of inst {}, module {} is not defined", inst.inst_name, inst.module_name),
+            }
+        }
+        module.add_param_list(params);
+        module
+    }
+
+    ///
+    /// create port of parsed instance, wires are not registered by WireBuilder
+    /// the port is returned with the item of `expr` which can not be connected
+    ///
+    fn build_inst_port(
+        name: &str,
+        def_port: Option<&VerilogPort>,
+        expr: Option<&str>,
+        params: &Vec<Param>,
+        parent_params: &Vec<Param>,
+    ) -> (VerilogPort, Option<String>) {
+        let (inout, width) = match def_port {
+            Some(p) => (p.inout, p.width.width_from(params)),
+            None => (PortDir::Unknown, RawWidth(0)),
+        };
+        let mut port = VerilogPort::new(inout, name, width);
+        if let Some(p) = def_port {
+            port.unpacked = p.unpacked.iter().map(|d| d.width_from(params)).collect();
+            port.unresolved = p.unresolved;
+            port.data_type = p.data_type.clone();
+        }
+        if let Some(p) = def_port.filter(|p| p.is_interface()) {
+            port.interface = p.interface.clone();
+            if let Some(text) = expr {
+                port.connect_interface(text);
+            }
+            return (port, None);
+        }
+        let res = expr.map_or(Ok(()), |text| port.connect_detached_expr(text, parent_params));
+        if def_port.is_none() {
+            port.width = RawWidth(port.get_connected_width());
+        }
+        (port, res.err())
+    }
+
+    ///
+    /// evaluate parameter override, which may refer to parameters of parent module
+    ///
+    fn eval_param(text: &str, parent_params: &Vec<Param>) -> Option<ParamValue> {
+        if let Some(p) = parent_params.iter().find(|p| p.name == text) {
+            return Some(p.clone().into());
+        }
+        if let Some(v) = ParamValue::from_literal(text) {
+            return Some(v);
+        }
+        Param::eval_expr(text, parent_params).map(|v| v.into())
+    }
+
+    fn extract_ansi_params(&self, module_node: RefNode) -> Vec<Param> {
+        log::debug!("start extract ansi parameters");
+        let mut params = Vec::new();
+        if let Some(list) = unwrap_node!(module_node.clone(), ParameterPortListDeclaration) {
+            self.collect_params(list, false, &mut params);
+        }
+        // `parameter` in body is localparam if the module has parameter port list
+        let body_local = !params.is_empty();
+        self.collect_params(module_node, body_local, &mut params);
         params
     }
 
     fn extract_params(&self, node: RefNode) -> Vec<Param> {
         log::debug!("start extract non-ansi params");
         let mut params = Vec::new();
-        for item in node.into_iter() {
-            if let RefNode::ParameterDeclaration(para) = item {
-                let token = if let Some(id) = unwrap_node!(para, ParameterIdentifier) {
-                    self.get_identifier_string(id)
-                } else { None };
-                let value = if let Some(t) = unwrap_node!(para, ConstantExpression) {
-                    self.get_literal_string(t)
-                } else { None };
-                if token.is_some() && value.is_some() {
-                    log::debug!("Find parameter token is {:?}, value is {:?}", token, value);
-                    params.push(Param::new(token.unwrap(), value.unwrap().width()));
-                } else {
-                    log::debug!("Error find parameter token is {:?}, value is {:?}", token, value);
+        self.collect_params(node, false, &mut params);
+        params
+    }
+
+    ///
+    /// parameters & localparams in declaration order, the ones already in `params` are skipped
+    /// value of expression is calculated by the former parameters
+    /// string, sized number & real are kept verbatim, `parameter type` keeps the data type
+    ///
+    fn collect_params(&self, node: RefNode, all_local: bool, params: &mut Vec<Param>) {
+        let mut locals = HashSet::new();
+        let mut decl_types = HashMap::new();
+        for n in node.clone().into_iter() {
+            let (decl, local) = match n {
+                RefNode::LocalParameterDeclaration(d) => (RefNode::from(d), true),
+                RefNode::ParameterDeclaration(d) => (RefNode::from(d), false),
+                RefNode::ParameterPortDeclaration(d) => (RefNode::from(d), false),
+                _ => continue,
+            };
+            let data_type = self.param_decl_type(decl.clone());
+            for name in self.param_names(decl) {
+                if local {
+                    locals.insert(name.clone());
+                }
+                if let Some(t) = &data_type {
+                    decl_types.insert(name, t.clone());
                 }
             }
         }
-        params
+        for n in node.into_iter() {
+            let (token, param) = match n {
+                RefNode::ParamAssignment(para) => {
+                    let token = unwrap_node!(para, ParameterIdentifier).and_then(|id| self.get_identifier_string(id));
+                    let text = unwrap_node!(para, ConstantParamExpression).and_then(|e| self.get_node_text(e));
+                    let (Some(token), Some(text)) = (token, text) else {
+                        log::debug!("Error find parameter in {:?}", self.get_node_text(RefNode::from(para)));
+                        continue;
+                    };
+                    let param = match ParamValue::from_literal(&text) {
+                        Some(v) => Param::new_with_param(token.clone(), v),
+                        None => {
+                            let value = Param::eval_expr(&text, params).unwrap_or_else(|| {
+                                log::warn!("can not evaluate parameter {} = {}, 0 is used", token, text);
+                                0
+                            });
+                            Param::new(token.clone(), value).with_expr(text)
+                        }
+                    };
+                    let macros = self.macros_of(RefNode::from(para), &token);
+                    (token, param.with_macros(macros.into_iter().rev().collect()))
+                }
+                RefNode::TypeAssignment(ta) => {
+                    let Some(token) = self.get_identifier_string(RefNode::from(&ta.nodes.0)) else { continue };
+                    let Some(text) = ta.nodes.1.as_ref().and_then(|(_, t)| self.get_node_text(RefNode::from(t))) else {
+                        log::warn!("type parameter {} has no default type, it is dropped", token);
+                        continue;
+                    };
+                    let param = Param::new_with_param(token.clone(), ParamValue::Type(text));
+                    let macros = self.macros_of(RefNode::from(ta), &token);
+                    (token, param.with_macros(macros.into_iter().rev().collect()))
+                }
+                _ => continue,
+            };
+            if params.iter().any(|p| p.name == token) {
+                continue;
+            }
+            log::debug!("Find parameter {:?}", param);
+            let local = all_local || locals.contains(&token);
+            params.push(param.with_local(local).with_type(decl_types.get(&token).cloned()));
+        }
+    }
+
+    ///
+    /// declared type of parameter declaration, `type` for type parameter, None for implicit type
+    ///
+    fn param_decl_type(&self, decl: RefNode) -> Option<String> {
+        match unwrap_node!(decl, TypeAssignment, DataTypeOrImplicit, DataType) {
+            Some(RefNode::TypeAssignment(_)) => Some("type".into()),
+            Some(n) => self.get_node_text(n),
+            None => None,
+        }
+    }
+
+    fn param_names(&self, decl: RefNode) -> Vec<String> {
+        decl.into_iter()
+            .filter_map(|n| match n {
+                RefNode::ParamAssignment(pa) => unwrap_node!(pa, ParameterIdentifier).and_then(|id| self.get_identifier_string(id)),
+                RefNode::TypeAssignment(ta) => self.get_identifier_string(RefNode::from(&ta.nodes.0)),
+                _ => None,
+            })
+            .collect()
     }
 
-    fn extract_ports(&self, module_node: RefNode, params: &Vec<Param>) -> Vec<VerilogData<VerilogPort>> {
+    fn extract_ports(&self, module_node: RefNode, params: &Vec<Param>, types: &HashMap<String, usize>) -> Vec<VerilogData<VerilogPort>> {
         log::debug!("start non-ansi extract ports");
         let mut port_list = Vec::new();
         for item in module_node.into_iter() {
             if let RefNode::PortDeclaration(port_dir) = item {
+                if let PortDeclaration::Interface(x) = port_dir {
+                    port_list.extend(self.extract_interface_ports(&x.nodes.1));
+                    continue;
+                }
                 //port direction
                 let inout = Self::get_direction(port_dir);
 
                 //port width
                 let width = self.get_port_width(RefNode::from(port_dir));
                 let width = width.width_from(params);
+                let (data_type, width) = self.get_port_type(RefNode::from(port_dir), width, types);
 
-                // port name
-                for port_node in unwrap_node!(port_dir, ListOfPortIdentifiers)
+                // port name, unpacked dimensions follow the name
+                // `input req_t req` is a variable declaration
+                let mut ports: Vec<VerilogPort> = Vec::new();
+                for port_node in unwrap_node!(port_dir, ListOfPortIdentifiers, ListOfVariableIdentifiers)
                     .into_iter()
                     .flatten()
                 {
-                    if let RefNode::PortIdentifier(t) = port_node {
-                        let port_name = self
-                            .get_identifier_string(RefNode::from(t))
-                            .unwrap_or_else(|| {
-                                log::error!("Can not extract port name");
-                                "".into()
-                            });
-                        let port_inst = VerilogPort::new(inout.clone(), &port_name, width.clone());
-                        port_list.push(port_inst.wrap_raw());
+                    match port_node {
+                        RefNode::PortIdentifier(_) | RefNode::VariableIdentifier(_) => {
+                            let port_name = self
+                                .get_identifier_string(port_node)
+                                .unwrap_or_else(|| {
+                                    log::error!("Can not extract port name");
+                                    "".into()
+                                });
+                            let mut port = VerilogPort::new(inout, &port_name, width.clone());
+                            port.data_type = data_type.clone();
+                            ports.push(port);
+                        }
+                        RefNode::UnpackedDimension(dim) => {
+                            if let Some(p) = ports.last_mut() {
+                                p.unpacked.push(self.get_unpacked_dim(dim, params).width_from(params));
+                            }
+                        }
+                        _ => {}
                     }
                 }
+                let comment = self.comment_of(RefNode::from(port_dir));
+                for mut p in ports {
+                    if !comment.is_empty() {
+                        p.set_info_msg(&comment);
+                    }
+                    let macros = self.macros_of(RefNode::from(port_dir), &p.name);
+                    port_list.push(p.wrap_macro_with(macros));
+                }
             }
         }
         log::debug!("end  non-ansi extract ports");
         port_list
     }
 
-    fn extract_ansi_ports(&self, module_node: RefNode, params: &Vec<Param>) -> Vec<VerilogData<VerilogPort>> {
+    ///
+    /// `axi_if.master m0, m1;` in non-ansi port declaration
+    ///
+    fn extract_interface_ports(&self, decl: &InterfacePortDeclaration) -> Vec<VerilogData<VerilogPort>> {
+        let mut interface = self.get_identifier_string(RefNode::from(&decl.nodes.0)).unwrap_or_default();
+        if let Some((_, modport)) = &decl.nodes.1
+            && let Some(modport) = self.get_identifier_string(RefNode::from(modport))
+        {
+            interface = format!("{}.{}", interface, modport);
+        }
+        let mut port_list = Vec::new();
+        for node in RefNode::from(&decl.nodes.2).into_iter() {
+            if let RefNode::InterfaceIdentifier(t) = node
+                && let Some(name) = self.get_identifier_string(RefNode::from(t))
+            {
+                let mut port = VerilogPort::new_interface(&name, &interface);
+                let comment = self.comment_of(RefNode::from(decl));
+                if !comment.is_empty() {
+                    port.set_info_msg(&comment);
+                }
+                let macros = self.macros_of(RefNode::from(decl), &name);
+                port_list.push(port.wrap_macro_with(macros));
+            }
+        }
+        port_list
+    }
+
+    fn extract_ansi_ports(&self, module_node: RefNode, params: &Vec<Param>, types: &HashMap<String, usize>) -> Vec<VerilogData<VerilogPort>> {
         log::debug!("start extract ansi ports");
         let mut port_list = Vec::new();
         for item in module_node.into_iter() {
@@ -223,6 +1075,8 @@ impl<'a> VerilogParser<'a> {
                     unwrap_node!(port_dir, PortDirection)
                 {
                     Self::get_ansi_direction(dir)
+                } else if unwrap_node!(port_dir, InterfacePortHeader).is_some() {
+                    PortDir::InterfacePort
                 } else {
                     log::error!("Can not extract ansi port direction");
                     PortDir::Unknown
@@ -230,6 +1084,7 @@ impl<'a> VerilogParser<'a> {
 
                 let width = self.get_port_width(RefNode::from(port_dir));
                 let width = width.width_from(params);
+                let (data_type, width) = self.get_port_type(RefNode::from(port_dir), width, types);
 
                 let port_name = if let Some(id) = unwrap_node!(port_dir, PortIdentifier) {
                     self.get_identifier_string(id).unwrap_or_else(|| {
@@ -241,33 +1096,313 @@ impl<'a> VerilogParser<'a> {
                     "".into()
                 };
                 log::debug!("extract port name is {}", port_name);
+                let comment = self.comment_of(RefNode::from(port_dir));
 
-                let port_inst = VerilogPort::new(inout, &port_name, width);
-                port_list.push(port_inst.wrap_raw());
+                if inout == PortDir::InterfacePort {
+                    // `axi_if.master m_axi`, or `interface m_axi` without type
+                    let interface = unwrap_node!(port_dir, InterfacePortHeader)
+                        .and_then(|h| self.get_node_text(h))
+                        .map(|s| s.split_whitespace().collect::<String>())
+                        .unwrap_or_else(|| "interface".into());
+                    let mut port = VerilogPort::new_interface(&port_name, &interface);
+                    if !comment.is_empty() {
+                        port.set_info_msg(&comment);
+                    }
+                    let macros = self.macros_of(RefNode::from(port_dir), &port_name);
+                    port_list.push(port.wrap_macro_with(macros));
+                    continue;
+                }
+
+                let mut port_inst = VerilogPort::new(inout, &port_name, width);
+                port_inst.data_type = data_type;
+                for node in RefNode::from(port_dir).into_iter() {
+                    if let RefNode::UnpackedDimension(dim) = node {
+                        port_inst.unpacked.push(self.get_unpacked_dim(dim, params).width_from(params));
+                    }
+                }
+                if !comment.is_empty() {
+                    port_inst.set_info_msg(&comment);
+                }
+                let macros = self.macros_of(RefNode::from(port_dir), &port_name);
+                port_list.push(port_inst.wrap_macro_with(macros));
             }
         }
         port_list
     }
 
+    ///
+    /// size of unpacked dimension, `[4]` and `[0:3]` are both 4
+    ///
+    fn get_unpacked_dim(&self, dim: &UnpackedDimension, params: &Vec<Param>) -> Width {
+        match dim {
+            UnpackedDimension::Range(x) => {
+                let range = &x.nodes.0.nodes.1;
+                let left = self.extract_expr(&range.nodes.0).width_from(params);
+                let right = self.extract_expr(&range.nodes.2).width_from(params);
+                // both `[0:N-1]` & `[N-1:0]` are allowed, the size is the distance of the bounds
+                if left.width() >= right.width() {
+                    left - right + 1
+                } else {
+                    right - left + 1
+                }
+            }
+            UnpackedDimension::Expression(x) => self.extract_expr(&x.nodes.0.nodes.1),
+        }
+    }
+
+    ///
+    /// width of all the packed dimensions, `[N-1:0][7:0]` is `Packed([N, 8])`
+    ///
     fn get_port_width(&self, port_node: RefNode) -> Width {
         log::debug!("extract port width >>>");
-        if let Some(range) = unwrap_node!(port_node, PackedDimension) {
+        let mut dims = Vec::new();
+        for node in port_node.into_iter() {
+            let RefNode::PackedDimension(range) = node else { continue };
             log::debug!("find node {:?}", range);
-            if let Some(RefNode::ConstantRange(range)) = unwrap_node!(range, ConstantRange) {
-                let upper = self.extract_expr(&range.nodes.0);
-                let lower = self.extract_expr(&range.nodes.2);
+            dims.push(self.get_packed_dim(range));
+        }
+        match dims.len() {
+            0 => RawWidth(1),
+            1 => dims.remove(0),
+            _ => Width::Packed(dims),
+        }
+    }
 
-                log::debug!("port range upper: {:?} and lower: {:?}", upper, lower);
-                upper - lower + 1
-            } else {
-                log::debug!("[extract width] Cannot find node ConstantRange");
-                RawWidth(1)
-            }
+    fn get_packed_dim(&self, dim: &PackedDimension) -> Width {
+        if let Some(RefNode::ConstantRange(range)) = unwrap_node!(dim, ConstantRange) {
+            let upper = self.extract_expr(&range.nodes.0);
+            let lower = self.extract_expr(&range.nodes.2);
+
+            log::debug!("port range upper: {:?} and lower: {:?}", upper, lower);
+            upper - lower + 1
         } else {
+            log::debug!("[extract width] Cannot find node ConstantRange");
             RawWidth(1)
         }
     }
 
+    fn packed_width<'b>(&self, dims: impl Iterator<Item = &'b PackedDimension>, params: &Vec<Param>) -> usize {
+        dims.map(|d| self.get_packed_dim(d).width_from(params).width()).product()
+    }
+
+    ///
+    /// width of port declared with data type, such as `my_pkg::req_t` or `int`
+    /// packed dimensions of the port are outer of the type, type name is None for builtin types
+    ///
+    fn get_port_type(&self, port_node: RefNode, width: Width, types: &HashMap<String, usize>) -> (Option<String>, Width) {
+        let (name, type_width) = match unwrap_node!(port_node.clone(), DataType, NetTypeIdentifier) {
+            Some(RefNode::DataType(DataType::Atom(a))) => {
+                (None, self.get_node_text(RefNode::from(&a.nodes.0)).and_then(|s| Self::atom_width(&s)))
+            }
+            Some(RefNode::DataType(dt @ (DataType::Type(_) | DataType::ClassType(_)))) => {
+                let Some(name) = self.type_name(RefNode::from(dt)) else { return (None, width) };
+                let type_width = self.lookup_type(&name, types);
+                (self.visible_type_name(&name, types), type_width)
+            }
+            Some(RefNode::NetTypeIdentifier(id)) => {
+                let Some(name) = self.get_identifier_string(RefNode::from(id)) else { return (None, width) };
+                let type_width = self.lookup_type(&name, types);
+                (self.visible_type_name(&name, types), type_width)
+            }
+            _ => return (None, width),
+        };
+        let Some(type_width) = type_width else {
+            log::warn!("can not resolve width of type {}, it is not found in parsed packages", name.as_deref().unwrap_or_default());
+            return (name, width);
+        };
+        if unwrap_node!(port_node, PackedDimension).is_none() {
+            return (name, RawWidth(type_width));
+        }
+        let mut dims = match width {
+            Width::Packed(dims) => dims,
+            w => vec![w],
+        };
+        dims.push(RawWidth(type_width));
+        (name, Width::Packed(dims))
+    }
+
+    ///
+    /// `my_pkg::req_t [1:0]` => `my_pkg::req_t`
+    ///
+    fn type_name(&self, node: RefNode) -> Option<String> {
+        let text = self.get_node_text(node)?;
+        let name = text.split('[').next().unwrap_or_default();
+        Some(name.split_whitespace().collect())
+    }
+
+    fn atom_width(name: &str) -> Option<usize> {
+        match name {
+            "byte" => Some(8),
+            "shortint" => Some(16),
+            "int" | "integer" => Some(32),
+            "longint" | "time" => Some(64),
+            _ => None,
+        }
+    }
+
+    ///
+    /// width of type, local types are found first, then packages by scope or `import`
+    ///
+    fn lookup_type(&self, name: &str, local: &HashMap<String, usize>) -> Option<usize> {
+        if let Some(w) = local.get(name).or_else(|| self.types.get(name)) {
+            return Some(*w);
+        }
+        if name.contains("::") {
+            return None;
+        }
+        self.imports.iter().find_map(|item| match item.split_once("::") {
+            Some((pkg, "*")) => self.types.get(&format!("{}::{}", pkg, name)).copied(),
+            Some((_, t)) if t == name => self.types.get(item).copied(),
+            _ => None,
+        })
+    }
+
+    ///
+    /// name of the type in the generated top, imported type is qualified by its package
+    /// typedef in module or compilation unit is not visible there, only the width is kept
+    ///
+    fn visible_type_name(&self, name: &str, local: &HashMap<String, usize>) -> Option<String> {
+        if name.contains("::") {
+            return Some(name.into());
+        }
+        if local.contains_key(name) || self.types.contains_key(name) {
+            return None;
+        }
+        let imported = self.imports.iter().find_map(|item| match item.split_once("::") {
+            Some((pkg, "*")) => {
+                let full = format!("{}::{}", pkg, name);
+                self.types.contains_key(&full).then_some(full)
+            }
+            Some((_, t)) if t == name => Some(item.clone()),
+            _ => None,
+        });
+        // unknown type is kept as written, its width is not resolved either
+        imported.or_else(|| Some(name.into()))
+    }
+
+    ///
+    /// width of data type, None if it contains unknown type or non-integral type
+    /// struct is the sum of members & union is the widest member
+    ///
+    fn data_type_width(&self, data_type: &DataType, params: &Vec<Param>, types: &HashMap<String, usize>) -> Option<usize> {
+        match data_type {
+            DataType::Vector(v) => Some(self.packed_width(v.nodes.2.iter(), params)),
+            DataType::Atom(a) => Self::atom_width(&self.get_node_text(RefNode::from(&a.nodes.0))?),
+            DataType::StructUnion(s) => {
+                let (_, (first, rest), _) = &s.nodes.2.nodes;
+                let mut widths = Vec::new();
+                for member in std::iter::once(first).chain(rest.iter()) {
+                    let DataTypeOrVoid::DataType(t) = &member.nodes.2 else { continue };
+                    let count = RefNode::from(&member.nodes.3)
+                        .into_iter()
+                        .filter(|n| matches!(n, RefNode::VariableDeclAssignment(_)))
+                        .count();
+                    widths.push(self.data_type_width(t, params, types)? * count);
+                }
+                let width = match s.nodes.0 {
+                    StructUnion::Struct(_) => widths.iter().sum(),
+                    _ => widths.iter().copied().max().unwrap_or(0),
+                };
+                Some(width * self.packed_width(s.nodes.3.iter(), params))
+            }
+            DataType::Enum(e) => {
+                // base type is `int` by default
+                let base = match &e.nodes.1 {
+                    None => 32,
+                    Some(b) => match unwrap_node!(b, IntegerAtomType, TypeIdentifier) {
+                        Some(RefNode::IntegerAtomType(a)) => Self::atom_width(&self.get_node_text(RefNode::from(a))?)?,
+                        Some(RefNode::TypeIdentifier(t)) => self.lookup_type(&self.get_identifier_string(RefNode::from(t))?, types)?,
+                        _ => {
+                            let dims = RefNode::from(b).into_iter().filter_map(|n| match n {
+                                RefNode::PackedDimension(d) => Some(d),
+                                _ => None,
+                            });
+                            self.packed_width(dims, params)
+                        }
+                    },
+                };
+                Some(base * self.packed_width(e.nodes.3.iter(), params))
+            }
+            DataType::Type(_) | DataType::ClassType(_) => {
+                let width = self.lookup_type(&self.type_name(RefNode::from(data_type))?, types)?;
+                let dims = RefNode::from(data_type).into_iter().filter_map(|n| match n {
+                    RefNode::PackedDimension(d) => Some(d),
+                    _ => None,
+                });
+                Some(width * self.packed_width(dims, params))
+            }
+            _ => None,
+        }
+    }
+
+    ///
+    /// `typedef` in the node, widths are added to `types` so later types can refer to them
+    ///
+    fn extract_typedefs(&self, node: RefNode, params: &Vec<Param>, types: &mut HashMap<String, usize>) {
+        for n in node.into_iter() {
+            // default type of `parameter type` is resolved like typedef
+            let (name, data_type) = match n {
+                RefNode::TypeDeclaration(TypeDeclaration::DataType(t)) => (self.get_identifier_string(RefNode::from(&t.nodes.2)), &t.nodes.1),
+                RefNode::TypeAssignment(ta) => match &ta.nodes.1 {
+                    Some((_, t)) => (self.get_identifier_string(RefNode::from(&ta.nodes.0)), t),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let Some(name) = name else { continue };
+            match self.data_type_width(data_type, params, types) {
+                Some(w) => {
+                    log::debug!("type {} is {} bits", name, w);
+                    types.insert(name, w);
+                }
+                None => log::warn!("can not resolve width of type {}", name),
+            }
+        }
+    }
+
+    ///
+    /// widths of the types declared in packages of this file, named as `pkg::type`
+    ///
+    pub fn packages(&self) -> HashMap<String, usize> {
+        let mut res = HashMap::new();
+        let Some(tree) = self.parse_res.as_ref() else { return res };
+        for node in tree {
+            let RefNode::PackageDeclaration(pkg) = node else { continue };
+            let Some(name) = unwrap_node!(pkg, PackageIdentifier).and_then(|id| self.get_identifier_string(id)) else { continue };
+            // parameters of package, they may refer to the former ones
+            let mut params: Vec<Param> = Vec::new();
+            self.collect_params(RefNode::from(pkg), true, &mut params);
+            let mut types = HashMap::new();
+            self.extract_typedefs(RefNode::from(pkg), &params, &mut types);
+            res.extend(types.into_iter().map(|(t, w)| (format!("{}::{}", name, t), w)));
+        }
+        res
+    }
+
+    ///
+    /// packages & imports of this file, and types declared out of module
+    ///
+    fn extract_types(&mut self) {
+        let packages = self.packages();
+        self.types.extend(packages);
+        let Some(tree) = self.parse_res.as_ref() else { return };
+        self.imports = tree
+            .into_iter()
+            .filter_map(|node| match node {
+                RefNode::PackageImportItem(item) => self.get_node_text(RefNode::from(item)),
+                _ => None,
+            })
+            .map(|s| s.split_whitespace().collect())
+            .collect();
+        let mut types = HashMap::new();
+        for node in tree {
+            if let RefNode::Description(Description::PackageItem(item)) = node {
+                self.extract_typedefs(RefNode::from(item.as_ref()), &Vec::new(), &mut types);
+            }
+        }
+        self.types.extend(types);
+    }
+
     fn extract_expr(&self, expr: &ConstantExpression) -> Width {
         match unwrap_node!(
             expr,
@@ -295,7 +1430,7 @@ impl<'a> VerilogParser<'a> {
                     (RawWidth(x), RawWidth(y)) => {
                         match op.as_str() {
                             "+" => RawWidth(x+y),
-                            "-" => RawWidth(x-y),
+                            "-" if x >= y => RawWidth(x-y),
                             _ => format!("{}{}{}", left, op, right).into()
                         }
                     }  
@@ -322,8 +1457,11 @@ impl<'a> VerilogParser<'a> {
             PortDeclaration::Inout(_) => PortDir::InOutPort,
             PortDeclaration::Input(_) => PortDir::InPort,
             PortDeclaration::Output(_) => PortDir::OutPort,
-            PortDeclaration::Ref(_) => PortDir::Unknown,
-            PortDeclaration::Interface(_) => PortDir::Unknown,
+            PortDeclaration::Ref(_) => {
+                log::warn!("ref port is not supported, direction is unknown");
+                PortDir::Unknown
+            }
+            PortDeclaration::Interface(_) => PortDir::InterfacePort,
         }
     }
 
@@ -332,7 +1470,10 @@ impl<'a> VerilogParser<'a> {
             PortDirection::Input(_) => PortDir::InPort,
             PortDirection::Output(_) => PortDir::OutPort,
             PortDirection::Inout(_) => PortDir::InOutPort,
-            PortDirection::Ref(_) => PortDir::Unknown,
+            PortDirection::Ref(_) => {
+                log::warn!("ref port is not supported, direction is unknown");
+                PortDir::Unknown
+            }
         }
     }
 
@@ -371,7 +1512,8 @@ impl<'a> VerilogParser<'a> {
                 .as_ref()
                 .unwrap()
                 .get_str(&locate)
-                .map(|s| s.parse::<usize>().unwrap().into())
+                .and_then(|s| s.parse::<usize>().ok())
+                .map(|n| n.into())
         } else {
             None
         }
@@ -405,6 +1547,35 @@ impl<'a> VerilogParser<'a> {
         }
     }
 
+    ///
+    /// source text of a node, white spaces & comments around it are dropped
+    ///
+    fn get_node_text(&self, node: RefNode) -> Option<String> {
+        let tree = self.parse_res.as_ref()?;
+        let mut spaces = HashSet::new();
+        let mut tokens: Vec<Locate> = Vec::new();
+        for n in node.into_iter() {
+            match n {
+                RefNode::WhiteSpace(ws) => {
+                    for x in RefNode::from(ws).into_iter() {
+                        if let RefNode::Locate(l) = x {
+                            spaces.insert(l.offset);
+                        }
+                    }
+                }
+                RefNode::Locate(l) if !spaces.contains(&l.offset) => tokens.push(*l),
+                _ => {}
+            }
+        }
+        let (first, last) = (tokens.first()?, tokens.last()?);
+        let locate = Locate {
+            offset: first.offset,
+            line: first.line,
+            len: last.offset + last.len - first.offset,
+        };
+        tree.get_str(&locate).map(|s| s.trim().to_string())
+    }
+
     fn get_identifier_string(&self, node: RefNode) -> Option<String> {
         let locate = match unwrap_node!(node, SimpleIdentifier, EscapedIdentifier) {
             Some(RefNode::SimpleIdentifier(x)) => Some(x.nodes.0),
@@ -419,16 +1590,34 @@ impl<'a> VerilogParser<'a> {
     }
 }
 
+///
+/// parse number such as `8'hff`, return (width, value)
+///
+pub(crate) fn parse_sized_number(s: &str) -> Option<(u8, u128)> {
+    let number_re = Regex::new(r"^(\d+)\s*'\s*[sS]?([bodhBODH])\s*([0-9a-fA-F_]+)$").unwrap();
+    let c = number_re.captures(s)?;
+    let width = c[1].parse::<u8>().ok()?;
+    let base = match c[2].to_ascii_lowercase().as_str() {
+        "b" => 2,
+        "o" => 8,
+        "h" => 16,
+        _ => 10,
+    };
+    let value = u128::from_str_radix(&c[3].replace('_', ""), base).ok()?;
+    Some((width, value))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
     fn test_base() {
-        simple_logger::init_with_level(log::Level::Debug).unwrap();
+        let _ = simple_logger::init_with_level(log::Level::Debug);
         // let module_info = VerilogParser::new(&PathBuf::from("./test/npu_afifo_r.sv"))
         let module_info = VerilogParser::new(&PathBuf::from("./test/std-7.1.6-primitives.v"))
             .parse()
+            .unwrap()
             .solve()
             .get_module_info();
         for m in module_info {
@@ -437,4 +1626,269 @@ mod test {
             println!("module port number is {}", m.port_list.len())
         }
     }
+
+    #[test]
+    fn test_macro_width() {
+        let module_info = VerilogParser::new(&PathBuf::from("./test/std-7.1.6-primitives.v"))
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let test1 = module_info[0].port_list.iter().find(|p| p.name == "test1").unwrap();
+        assert_eq!(test1.width.width(), 3);
+
+        let file = PathBuf::from("./test/macro_width.v");
+        let module_info = VerilogParser::new(&file)
+            .add_headers(vec![PathBuf::from("./test/macro_defs.vh")])
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let din = &module_info[0].port_list[0];
+        assert_eq!(din.width.width(), 8);
+        assert!(!din.unresolved);
+        let dout = &module_info[0].port_list[1];
+        assert!(dout.unresolved);
+        assert!(dout.width.to_string().contains("`UNDEF_W"));
+    }
+
+    #[test]
+    fn test_define() {
+        let file = PathBuf::from("./test/npu_afifo_r.sv");
+        let module_info = VerilogParser::new(&file)
+            .add_define("test_macro", None)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let read_valid = module_info[0].port_list.iter().find(|p| p.name == "read_valid").unwrap();
+        assert_eq!(read_valid.get_macro_name(), "test_macro");
+        let read_accept = module_info[0].port_list.iter().find(|p| p.name == "read_accept").unwrap();
+        assert_eq!(read_accept.get_macro_name(), "");
+        // commented out port is not the info of the next one
+        let rdata = module_info[0].port_list.iter().find(|p| p.name == "rdata").unwrap();
+        assert!(!rdata.info.contains("rdpnt"), "{}", rdata.info);
+    }
+
+    #[test]
+    fn test_guarded_port() {
+        // `test_macro` is not defined, the port in its `ifdef block is still kept
+        let file = PathBuf::from("./test/npu_afifo_r.sv");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let ports = &module_info[0].port_list;
+        let idx = ports.iter().position(|p| p.name == "read_valid").unwrap();
+        assert_eq!(ports[idx].get_macro_name(), "test_macro");
+        assert_eq!(ports.iter().filter(|p| p.name == "read_valid").count(), 1);
+        assert_eq!(ports[idx - 1].name, "read_soft_rst");
+    }
+
+    #[test]
+    fn test_param_slice_conn() {
+        let file = PathBuf::from("./test/conn_top.v");
+        let parser = VerilogParser::new(&file).parse().unwrap().solve();
+        // expression which is not a slice can not be connected
+        assert_eq!(parser.dropped_conns(), ["u_sub.en: `en & data_in[0]`"]);
+        let module_info = parser.get_module_info();
+        let top = module_info.iter().find(|m| m.module_name == "conn_top").unwrap();
+        let inst = top.inst_list[0].borrow();
+        let port = |name: &str| inst.port_list.iter().find(|p| p.name == name).unwrap().get_signal_string();
+        assert_eq!(port("din"), "data_in[15:8]");
+        assert_eq!(port("dout"), "data_out[7:0]");
+    }
+
+    #[test]
+    fn test_inst() {
+        let file = PathBuf::from("./test/inst_top.v");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let top = module_info.iter().find(|m| m.module_name == "inst_top").unwrap();
+        assert_eq!(top.inst_list.len(), 3);
+
+        let named = top.inst_list[0].borrow();
+        assert_eq!(named.inst_name.as_deref(), Some("u_named"));
+        assert_eq!(named.param_list[0].get_value(), 8);
+        assert_eq!(named.param_list[1].get_value(), 4);
+        let dout = named.port_list.iter().find(|p| p.name == "dout").unwrap();
+        assert_eq!(dout.width.width(), 8);
+        assert_eq!(dout.get_signal_string(), "{data_out[7:4], data_out[3:0]}");
+
+        let ordered = top.inst_list[1].borrow();
+        assert_eq!(ordered.param_list[1].name, "DEPTH");
+        let din = ordered.port_list.iter().find(|p| p.name == "din").unwrap();
+        assert_eq!(din.inout, PortDir::InPort);
+        assert_eq!(din.get_signal_string(), "data_in[15:8]");
+
+        let wildcard = top.inst_list[2].borrow();
+        assert_eq!(wildcard.port_list.len(), 4);
+        assert_eq!(wildcard.port_list[0].get_signal_string(), "clk");
+    }
+
+    #[test]
+    fn test_param_expr() {
+        let file = PathBuf::from("./test/param_top.sv");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let leaf = module_info.iter().find(|m| m.module_name == "param_leaf").unwrap();
+        let names: Vec<&str> = leaf.param_list.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["DEPTH", "ADDR_W", "LAST"]);
+        assert_eq!(leaf.param_list[1].get_value(), 4);
+        assert_eq!(leaf.param_list[1].get_name(), "$clog2(DEPTH)");
+        assert!(!leaf.param_list[1].local);
+        assert!(leaf.param_list[2].local);
+        assert_eq!(leaf.port_list[0].width.width(), 4);
+
+        // derived parameters follow the override of DEPTH
+        let top = module_info.iter().find(|m| m.module_name == "param_top").unwrap();
+        let inst = top.inst_list[0].borrow();
+        assert_eq!(inst.port_list[0].width.width(), 6);
+        assert_eq!(inst.port_list[1].width.width(), 64);
+        assert_eq!(inst.to_inst_string()[1].trim(), format!(".{:<20}({:<10})", "DEPTH", 64));
+    }
+
+    #[test]
+    fn test_typed_param() {
+        let file = PathBuf::from("./test/typed_param.sv");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let params = &module_info[0].param_list;
+        assert_eq!(params[0].value_string(), "\"rom.hex\"");
+        assert_eq!(params[0].data_type.as_deref(), Some("string"));
+        assert_eq!(params[1].value_string(), "32'hDEAD_BEEF");
+        assert_eq!(params[1].get_value(), 0xDEAD_BEEF);
+        assert_eq!(params[2].value_string(), "1.5");
+        assert!(params[3].is_type());
+        assert_eq!(params[3].value_string(), "logic [7:0]");
+
+        // port of type parameter keeps the resolved width only
+        let din = &module_info[0].port_list[0];
+        assert_eq!(din.width.width(), 8);
+        assert!(din.data_type.is_none());
+    }
+
+    #[test]
+    fn test_skip_pragma() {
+        let file = PathBuf::from("./test/skip_pragma.v");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let skip: Vec<bool> = module_info.iter().map(|m| m.skip).collect();
+        // the pragma inside the module body does not skip it
+        assert_eq!(skip, vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn test_packed() {
+        let file = PathBuf::from("./test/packed_top.sv");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let data = &module_info[0].port_list[0];
+        assert_eq!(data.width.width(), 32);
+        assert_eq!(data.width.dims(), vec![4, 8]);
+        assert_eq!(data.width.range_string(crate::config::Dialect::SystemVerilog), "[3:0][7:0]");
+        assert_eq!(data.width.range_string(crate::config::Dialect::Verilog), "[31  :0]");
+        assert_eq!(module_info[0].port_list[1].width.dims(), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_comment() {
+        let file = PathBuf::from("./test/comment_top.v");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let info: Vec<&str> = module_info[0].port_list.iter().map(|p| p.info.as_str()).collect();
+        assert_eq!(info, vec!["clock & reset system clock", "active low", "write data of the fifo", "fifo is full"]);
+        let info: Vec<&str> = module_info[1].port_list.iter().map(|p| p.info.as_str()).collect();
+        assert_eq!(info, vec!["input a", "input b", ""]);
+    }
+
+    #[test]
+    fn test_typed() {
+        let pkg = PathBuf::from("./test/types_pkg.sv");
+        let packages = VerilogParser::new(&pkg).parse().unwrap().packages();
+        assert_eq!(packages["bus_pkg::req_t"], 42);
+        assert_eq!(packages["bus_pkg::data_t"], 16);
+
+        let file = PathBuf::from("./test/typed_top.sv");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .with_packages(packages)
+            .solve()
+            .get_module_info();
+        let ports = &module_info[0].port_list;
+        assert_eq!(ports[1].data_type.as_deref(), Some("bus_pkg::req_t"));
+        assert_eq!(ports[1].width.width(), 42);
+        assert_eq!(ports[2].data_type.as_deref(), Some("bus_pkg::data_t"));
+        assert_eq!(ports[2].width.dims(), vec![4, 16]);
+        assert_eq!(ports[2].width_string(), "bus_pkg::data_t(4x16)");
+        assert_eq!(ports[3].data_type, None);
+        assert_eq!(ports[3].width.width(), 32);
+        assert_eq!(ports[4].data_type, None);
+        assert_eq!(ports[4].width.width(), 32);
+
+        // the generated top has no import, only package-qualified types are used
+        let text = module_info[0].to_module_string(crate::config::Dialect::SystemVerilog).join("\n");
+        assert!(text.contains("bus_pkg::data_t [3:0]"));
+        assert!(!text.contains("word_t"));
+    }
+
+    #[test]
+    fn test_unpacked() {
+        let file = PathBuf::from("./test/packed_top.sv");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let cfg = &module_info[0].port_list[2];
+        assert_eq!(cfg.width.width(), 8);
+        assert_eq!(cfg.array_dims(), vec![4]);
+        let rd_data = &module_info[0].port_list[3];
+        assert_eq!(rd_data.array_dims(), vec![2]);
+        assert_eq!(rd_data.width_string(), "32[2]");
+        assert_eq!(Width::element_names("rd_data", &rd_data.array_dims()), vec!["rd_data[0]", "rd_data[1]"]);
+        // ascending ranges do not start from 0
+        assert_eq!(module_info[0].port_list[4].array_dims(), vec![4]);
+        assert_eq!(module_info[0].port_list[5].array_dims(), vec![2]);
+    }
+
+    #[test]
+    fn test_interface() {
+        let file = PathBuf::from("./test/iface_top.sv");
+        let module_info = VerilogParser::new(&file)
+            .parse()
+            .unwrap()
+            .solve()
+            .get_module_info();
+        let top = module_info.iter().find(|m| m.module_name == "iface_top").unwrap();
+        assert!(top.port_list[1].is_interface());
+        assert_eq!(top.port_list[1].inout_string(), "axi_if.slave");
+        // `axi_if u_bus ()` is not a module instance
+        assert_eq!(top.inst_list.len(), 1);
+
+        let sub = top.inst_list[0].borrow();
+        let m_axi = sub.port_list.iter().find(|p| p.name == "m_axi").unwrap();
+        assert_eq!(m_axi.interface.as_deref(), Some("axi_if.master"));
+        assert_eq!(m_axi.get_signal_string(), "u_bus");
+    }
 }
@@ -7,11 +7,16 @@ use std::ops::Range;
 use std::sync::{LazyLock, Mutex};
 use std::{sync::Arc, vec};
 use std::hash::{Hash, Hasher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use strum::Display;
 use crate::verilog::data::{VerilogData, WrapMacro};
+use crate::verilog::diagnostic::{Diagnostic, DiagnosticCollector, DiagnosticKind, Endpoint};
 use crate::verilog::parameter::Param;
+use crate::verilog::parse::parse_sized_number;
 use crate::verilog::VerilogBase;
 use crate::verilog::width::Width;
+use crate::config::Dialect;
 
 #[derive(Debug, Default)]
 pub struct VerilogPort {
@@ -22,12 +27,21 @@ pub struct VerilogPort {
     pub info: String,
 
     pub signals: Vec<VerilogValue>,
+    /// interface & modport of interface port, such as `axi_if.master`
+    pub interface: Option<String>,
+    /// unpacked dimensions, `[4]` & `[0:3]` are both 4, `width` is the width of one element
+    pub unpacked: Vec<Width>,
+    /// width uses undefined macro, it is not trusted
+    pub unresolved: bool,
+    /// user defined type, such as `my_pkg::req_t`, `width` includes the bits of the type
+    pub data_type: Option<String>,
     has_undefine: u8,
     undefine_wires_idx: Vec<(usize, usize)>,
 
     health_checked: bool,
     undefine_registered: bool,
     main_port_flag:bool,   // indicate this port is main module port, not inst module port
+    scope: Option<String>, // the sheet which this port belongs to, used by diagnostics
 }
 impl VerilogPort {
     pub fn new(inout: PortDir, name: &str, width: Width) -> Self {
@@ -36,25 +50,111 @@ impl VerilogPort {
             name: String::from(name),
             width,
             signals: vec![VerilogValue::NONE],
+            scope: DiagnosticCollector::current_sheet(),
             ..Default::default()
         }
     }
 
+    ///
+    /// interface port, such as `axi_if.master m_axi`
+    /// it is connected to interface instance, and not checked bit by bit
+    ///
+    pub fn new_interface(name: &str, interface: &str) -> Self {
+        let mut port = Self::new(PortDir::InterfacePort, name, Width::RawWidth(0));
+        port.interface = Some(interface.into());
+        port
+    }
+
+    ///
+    /// new port with the same direction, name, width & interface
+    ///
+    fn new_like(p: &VerilogPort) -> Self {
+        let mut port = Self::new(p.inout, &p.name, p.width.clone());
+        port.interface = p.interface.clone();
+        port.unpacked = p.unpacked.clone();
+        port.unresolved = p.unresolved;
+        port.data_type = p.data_type.clone();
+        port
+    }
+
+    pub fn is_array(&self) -> bool {
+        !self.unpacked.is_empty()
+    }
+
+    pub fn array_dims(&self) -> Vec<usize> {
+        self.unpacked.iter().map(|d| d.width()).collect()
+    }
+
+    ///
+    /// string in Width column of excel
+    /// packed dimensions are joined by `x`, unpacked dimensions follow in brackets, such as `4x8[2]`
+    ///
+    pub fn width_string(&self) -> String {
+        let packed = self.width.dims().iter().map(|d| d.to_string()).collect::<Vec<_>>().join("x");
+        let unpacked = self.array_dims().iter().map(|d| format!("[{}]", d)).collect::<String>();
+        match &self.data_type {
+            Some(t) => format!("{}({}{})", t, packed, unpacked),
+            None => format!("{}{}", packed, unpacked),
+        }
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.inout == PortDir::InterfacePort
+    }
+
+    ///
+    /// string in InOut column of excel
+    /// interface & modport for interface port
+    ///
+    pub fn inout_string(&self) -> String {
+        match &self.interface {
+            Some(s) if self.is_interface() => s.clone(),
+            _ => self.inout.to_string(),
+        }
+    }
+
+    ///
+    /// set the sheet which this port belongs to
+    ///
+    pub fn set_scope(&mut self, scope: &str) {
+        self.scope = Some(scope.into());
+    }
+
+    fn endpoint(&self) -> Endpoint {
+        Endpoint::new(self.scope.clone(), &self.name)
+    }
+
     pub fn set_info_msg(&mut self, msg: &str) {
         self.info = format!("{}", msg)
     }
 
     pub fn register_port_as_wire(&mut self) {
         self.main_port_flag = true;
+        if self.is_interface() {
+            self.health_checked = true;
+            return;
+        }
         if self.signals.len() > 1 {
             self.check_health();
             return;
         }
-        match self.inout {
-            PortDir::InPort => WireBuilder::add_driver_wire_asport(&self.name, &(0..self.width.width()), false),
-            PortDir::OutPort => WireBuilder::add_load_wire_asport(&self.name, &(0..self.width.width()), false),
-            _ => WireBuilder::add_load_wire_asport(&self.name, &(0..self.width.width()), true), 
+        // every element of array is a wire
+        let names = if self.is_array() {
+            WireBuilder::add_array(&self.name, self.array_dims());
+            Width::element_names(&self.name, &self.array_dims())
+        } else {
+            vec![self.name.clone()]
         };
+        if let Some(t) = &self.data_type {
+            WireBuilder::add_type(&self.name, t);
+        }
+        for name in names.iter() {
+            match self.inout {
+                PortDir::InPort => WireBuilder::add_driver_wire_asport(name, &(0..self.width.width()), false, self.endpoint()),
+                PortDir::OutPort => WireBuilder::add_load_wire_asport(name, &(0..self.width.width()), false, self.endpoint()),
+                _ => WireBuilder::add_load_wire_asport(name, &(0..self.width.width()), true, self.endpoint()), 
+            };
+        }
         self.health_checked = true;
     }
 
@@ -63,13 +163,35 @@ impl VerilogPort {
     /// register wires by WireBuilder
     ///
     fn connect_wire(&self, sig: &str, range: &Range<usize>) -> Arc<VerilogWire> {
+        // wire connected to the whole port is declared with the same type & packed dimensions
+        if !sig.contains('[') && range.start == 0 && range.len() == self.width.width() {
+            if self.width.is_packed() {
+                WireBuilder::add_dims(sig, self.width.dims());
+            }
+            if let Some(t) = &self.data_type {
+                WireBuilder::add_type(sig, t);
+            }
+        }
+        // whole array is connected element by element, `sig[2]` is one element
+        if self.is_array() && !sig.contains('[') {
+            WireBuilder::add_array(sig, self.array_dims());
+            for name in Width::element_names(sig, &self.array_dims()) {
+                self.connect_element(&name, range);
+            }
+            return VerilogWire::array(sig);
+        }
+        self.connect_element(sig, range)
+    }
+
+    fn connect_element(&self, sig: &str, range: &Range<usize>) -> Arc<VerilogWire> {
+        let from = self.endpoint();
         match (self.main_port_flag, self.inout) {
-            (false, PortDir::InPort) => WireBuilder::add_load_wire(sig, range, false),
-            (false, PortDir::OutPort) => WireBuilder::add_driver_wire(sig, range, false),
-            (false, _) => WireBuilder::add_load_wire(sig, range, true), 
-            (true, PortDir::InPort) => WireBuilder::add_driver_wire(sig, range, false),
-            (true, PortDir::OutPort) => WireBuilder::add_load_wire(sig, range, false),
-            (true, _) => WireBuilder::add_driver_wire(sig, range, true),
+            (false, PortDir::InPort) => WireBuilder::add_load_wire(sig, range, false, from),
+            (false, PortDir::OutPort) => WireBuilder::add_driver_wire(sig, range, false, from),
+            (false, _) => WireBuilder::add_load_wire(sig, range, true, from), 
+            (true, PortDir::InPort) => WireBuilder::add_driver_wire(sig, range, false, from),
+            (true, PortDir::OutPort) => WireBuilder::add_load_wire(sig, range, false, from),
+            (true, _) => WireBuilder::add_driver_wire(sig, range, true, from),
         }
     }
 
@@ -90,7 +212,19 @@ impl VerilogPort {
     ///
     pub fn connect_self(&mut self) {
         let name = self.name.clone();
-        self.connect_undefined_signal(&name, false);
+        if self.is_interface() {
+            self.connect_interface(&name);
+        } else {
+            self.connect_undefined_signal(&name, false);
+        }
+    }
+
+    ///
+    /// connect interface instance to interface port
+    /// interface is not registered by WireBuilder
+    ///
+    pub fn connect_interface(&mut self, inst: &str) {
+        self.signals.push(VerilogValue::Interface(inst.into()));
     }
 
     ///
@@ -103,6 +237,44 @@ impl VerilogPort {
         self.signals.push(Wire(Arc::clone(&wire), range.clone()));
     }
 
+    ///
+    /// connect wire without registering by WireBuilder
+    /// used by instances parsed from rtl, wires are registered when the port is copied
+    ///
+    pub fn connect_detached_signal(&mut self, sig: &str, range: &Range<usize>) {
+        self.signals.push(Wire(VerilogWire::detached(sig), range.clone()));
+    }
+
+    ///
+    /// connect expression in rtl without registering by WireBuilder, such as
+    /// `wire`, `wire[3:0]`, `wire[DW-1:0]`, `wire[2]`, `4'h0` and `{wire1, wire2[1:0]}`
+    /// bounds of slice are evaluated with `params` of the parent module
+    /// the item which can not be connected is returned as error
+    ///
+    pub fn connect_detached_expr(&mut self, text: &str, params: &Vec<Param>) -> Result<(), String> {
+        let text = text.trim();
+        let text = text.strip_prefix('{').and_then(|s| s.strip_suffix('}')).unwrap_or(text);
+        let name_re = Regex::new(r"^[a-zA-Z_]\w*$").unwrap();
+        let slice_re = Regex::new(r"^([a-zA-Z_]\w*)\s*\[([^\[\]:]+)(?::([^\[\]:]+))?\]$").unwrap();
+        for item in text.split(',').map(|s| s.trim()) {
+            if let Some(c) = slice_re.captures(item) {
+                let msb = Param::eval_expr(&c[2], params);
+                let lsb = c.get(3).map_or(msb, |m| Param::eval_expr(m.as_str(), params));
+                match (msb, lsb) {
+                    (Some(msb), Some(lsb)) if msb >= lsb => self.connect_detached_signal(&c[1], &(lsb..(msb + 1))),
+                    _ => return Err(item.to_string()),
+                }
+            } else if let Some((width, value)) = parse_sized_number(item) {
+                self.connect_number_signal(value, width);
+            } else if name_re.is_match(item) {
+                self.connect_undefined_signal(item, false);
+            } else {
+                return Err(item.to_string());
+            }
+        }
+        Ok(())
+    }
+
     ///
     /// register const number which connected to this port
     ///
@@ -116,7 +288,7 @@ impl VerilogPort {
     ///
     /// get bit-width of the existing signal which connected to this port
     ///
-    fn get_connected_width(&self) -> usize {
+    pub fn get_connected_width(&self) -> usize {
         let mut width_sum = 0;
         for sig in self.signals.iter() {
             match sig {
@@ -140,7 +312,13 @@ impl VerilogPort {
         let width_sum = self.get_connected_width();
         let wire_infer_width = self.width.width() - width_sum;
         if wire_infer_width <= 0 {
-            log::warn!("[Infer Undefine-wire] Port {} has been over connected, port width is {}, but signal used already fill {} bits", self.name, self.width.width(), width_sum);
+            if !WireBuilder::is_quiet() {
+                log::warn!("[Infer Undefine-wire] Port {} has been over connected, port width is {}, but signal used already fill {} bits", self.name, self.width.width(), width_sum);
+            }
+            self.report(
+                DiagnosticKind::OverConnected,
+                format!("port {} has been over connected, port width is {}, but signal used already fill {} bits", self.name, self.width.width(), width_sum),
+            );
             return;
         }
         let (idx, signal) = self
@@ -159,13 +337,39 @@ impl VerilogPort {
     fn check_connected(&mut self) {
         let width_sum = self.get_connected_width();
         match self.width.width().cmp(&width_sum) {
-            Ordering::Greater => log::warn!("Port {} has not been full connected, port width is {} but signal width is {}", self.name,self.width.width(),width_sum),
-            Ordering::Less => log::warn!("Port {} has been over connected, port width is {} but signal width is {}", self.name, self.width.width(),width_sum),
+            Ordering::Greater => {
+                if !WireBuilder::is_quiet() {
+                    log::warn!("Port {} has not been full connected, port width is {} but signal width is {}", self.name,self.width.width(),width_sum);
+                }
+                self.report(
+                    DiagnosticKind::UnderConnected,
+                    format!("port {} has not been full connected, port width is {} but signal width is {}", self.name, self.width.width(), width_sum),
+                );
+            }
+            Ordering::Less => {
+                if !WireBuilder::is_quiet() {
+                    log::warn!("Port {} has been over connected, port width is {} but signal width is {}", self.name, self.width.width(),width_sum);
+                }
+                self.report(
+                    DiagnosticKind::OverConnected,
+                    format!("port {} has been over connected, port width is {} but signal width is {}", self.name, self.width.width(), width_sum),
+                );
+            }
             _ => {}
         }
         self.health_checked = true;
     }
 
+    ///
+    /// report port level finding to DiagnosticCollector
+    ///
+    fn report(&self, kind: DiagnosticKind, message: String) {
+        DiagnosticCollector::report(
+            Diagnostic::new(kind, &self.name, message)
+                .with_sources(vec![self.endpoint()]),
+        );
+    }
+
     ///
     /// process the undefine wires which connected to this port more than 1
     /// 通过 `HashMap<String, usize>`来注册，通过wire 名字得到她的索引
@@ -182,7 +386,7 @@ impl VerilogPort {
         }
         let width = self.get_connected_width();
         let infer_width = self.width.width() - width;
-        if infer_width <= 0 {
+        if infer_width <= 0 && !WireBuilder::is_quiet() {
             log::warn!("[Process Undefine-wire] Port {} has been over connected, port width is {}, bug signal used already fill {} bits", self.name, self.width.width(), width);
         }
         UndefineWireCollector::add_func(func_group, infer_width as i64);
@@ -211,6 +415,18 @@ impl VerilogPort {
         if self.health_checked {
             return;
         }
+        // width is unknown, it is reported as `UnresolvedWidth` already
+        if self.unresolved {
+            self.health_checked = true;
+            return;
+        }
+        if self.is_interface() {
+            if self.signals.len() > 2 && !WireBuilder::is_quiet() {
+                log::warn!("Interface port {} is connected to more than one interface", self.name);
+            }
+            self.health_checked = true;
+            return;
+        }
         match self.has_undefine {
             0 => self.check_connected(),
             1 => self.set_undefine_wire_1(),
@@ -229,7 +445,7 @@ impl VerilogPort {
     /// reconnect wires to new port
     /// 
     pub fn copy_inst_port_from(p: &VerilogData<VerilogPort>) -> VerilogData<Self> {
-        let mut new_port = VerilogPort::new(p.inout, &p.name, p.width.clone());
+        let mut new_port = VerilogPort::new_like(p);
         if p.info.len() > 0 {
             new_port.set_info_msg(&p.info)
         }
@@ -244,6 +460,9 @@ impl VerilogPort {
                 VerilogValue::Number { width, value } => {
                     new_port.connect_number_signal(*value, *width);
                 }
+                VerilogValue::Interface(s) => {
+                    new_port.connect_interface(s);
+                }
                 VerilogValue::NONE => {}
             }
         }
@@ -258,7 +477,7 @@ impl VerilogPort {
     /// without msg info
     /// 
     pub fn copy_inst_port_without_wire_from(p: &VerilogData<VerilogPort>) -> VerilogData<Self> {
-        let mut new_port = VerilogPort::new(p.inout, &p.name, p.width.clone());
+        let mut new_port = VerilogPort::new_like(p);
         new_port.connect_self();
         
         // dont check_health, since do this by function caller
@@ -267,7 +486,7 @@ impl VerilogPort {
     }
 
     pub fn copy_main_port_from(p: &VerilogPort) -> Self {
-        let mut new_port = VerilogPort::new(p.inout, &p.name, p.width.clone());
+        let mut new_port = VerilogPort::new_like(p);
         if p.info.len() > 0 {
             new_port.set_info_msg(&p.info)
         }
@@ -282,6 +501,9 @@ impl VerilogPort {
                 VerilogValue::Number { width, value } => {
                     new_port.connect_number_signal(*value, *width);
                 }
+                VerilogValue::Interface(s) => {
+                    new_port.connect_interface(s);
+                }
                 VerilogValue::NONE => {}
             }
         }
@@ -294,6 +516,7 @@ impl VerilogPort {
         if self.width.is_literal() {
             self.width = self.width.width_from(params)
         }
+        self.unpacked = self.unpacked.iter().map(|d| d.width_from(params)).collect();
     }
 
 
@@ -318,7 +541,8 @@ impl VerilogPort {
     /// port_name ({wire1, wire2})
     ///
     fn to_port_wire_string(&self) -> String {
-        let signal_string = self.get_signal_string();
+        // width of unresolved port is unknown, it is left unconnected
+        let signal_string = if self.unresolved { String::new() } else { self.get_signal_string() };
         format!(
             "{:<20} ({:<30})",
             self.name,
@@ -327,10 +551,11 @@ impl VerilogPort {
     }
 
     pub fn to_inst_string(&self, is_last: bool) -> Vec<String> {
-        let info = if self.info.len() > 0 {
-            format!(" // {}", self.info)
-        } else {
-            "".to_string()
+        let info = match (self.unresolved, self.info.is_empty()) {
+            (true, true) => " // width uses undefined macro, not connected".to_string(),
+            (true, false) => format!(" // width uses undefined macro, not connected. {}", self.info),
+            (false, false) => format!(" // {}", self.info),
+            (false, true) => "".to_string(),
         };
         if is_last {
             vec![format!(
@@ -347,38 +572,48 @@ impl VerilogPort {
         }
     }
 
-    pub fn to_port_string(&self, is_last: bool) -> Vec<String> {
+    pub fn to_port_string(&self, is_last: bool, dialect: Dialect) -> Vec<String> {
         let info = if self.info.len() > 0 {
             format!(" // {}", self.info)
         } else {
             "".to_string()
         };
-        let width = if self.width.width() < 2 {
-            " ".repeat(8)
+        if let (true, Some(interface)) = (self.is_interface(), &self.interface) {
+            let sep = if is_last { " " } else { "," };
+            return vec![format!("{:<10} {:<29}{}{}", interface, self.name, sep, info)];
+        }
+        let (net_type, width) = match &self.data_type {
+            Some(t) => (t.clone(), self.width.outer_range_string()),
+            None => (dialect.net_type().to_string(), self.width.range_string(dialect)),
+        };
+        let name = if self.is_array() {
+            format!("{} {}", self.name, Width::unpacked_string(&self.array_dims()))
         } else {
-            format!("[{:<4}:0]", self.width.width()-1)
+            self.name.clone()
         };
         if is_last {
             vec![format!(
-                "{:<10} wire {} {:<20}  {}",
+                "{:<10} {} {} {:<20}  {}",
                 self.inout,
+                net_type,
                 width,
-                self.name,
+                name,
                 info
             )]
         } else {
             vec![format!(
-                "{:<10} wire {} {:<20},{}",
+                "{:<10} {} {} {:<20},{}",
                 self.inout,
+                net_type,
                 width,
-                self.name,
+                name,
                 info
             )]
         }
     }
 
     pub fn to_assign_string(&self) -> Option<Vec<String>> {
-        if self.signals.len() > 1 {
+        if self.signals.len() > 1 && !self.is_interface() {
             Some(vec![format!(
                 "assign {:<20} = {:<30};",
                 self.name,
@@ -448,9 +683,11 @@ impl UndefineWireCollector {
 
     ///
     /// call this function after all the port are connected
+    /// return false if the wire width can not be inferred
     ///
-    pub fn solve_func() {
+    pub fn solve_func() -> bool {
         log::debug!("Solve Function start");
+        let quiet = WireBuilder::is_quiet();
         let mut collector = WIRECOLLECTOR.lock().unwrap();
         let num_vars = collector.wires.len();
         let new_func = collector
@@ -470,11 +707,22 @@ impl UndefineWireCollector {
 
         if let Some(res) = new_func {
             collector.res = res;
+            true
         } else {
-            log::error!(
-                "Can not infer wire-width from wires: \n{:#?}",
-                collector.wires
-            );
+            if !quiet {
+                log::error!(
+                    "Can not infer wire-width from wires: \n{:#?}",
+                    collector.wires
+                );
+            }
+            for name in collector.wires.keys() {
+                DiagnosticCollector::report(Diagnostic::new(
+                    DiagnosticKind::UnresolvedWire,
+                    name,
+                    format!("can not infer width of wire {}", name),
+                ));
+            }
+            false
         }
     }
 
@@ -492,7 +740,7 @@ impl UndefineWireCollector {
     }
 }
 
-#[derive(Debug, Default, Display, Clone, PartialEq, Hash, Eq, Copy)]
+#[derive(Debug, Default, Display, Clone, PartialEq, Hash, Eq, Copy, Serialize, Deserialize)]
 pub enum PortDir {
     #[strum(to_string = "input")]
     InPort,
@@ -503,6 +751,10 @@ pub enum PortDir {
     #[strum(to_string = "inout")]
     InOutPort,
 
+    /// interface & modport are kept in `VerilogPort::interface`
+    #[strum(to_string = "interface")]
+    InterfacePort,
+
     #[strum(to_string = "unknown")]
     Unknown,
 }
@@ -525,11 +777,14 @@ impl PortDir {
 
 impl From<&String> for PortDir {
     fn from(value: &String) -> Self {
-        let value = value.as_str();
+        let value = value.trim();
         match value {
             "input" => Self::InPort,
             "output" => Self::OutPort,
             "inout" => Self::InOutPort,
+            "interface" => Self::InterfacePort,
+            // `axi_if.master`, a bare word is not taken as interface to catch typo of direction
+            s if Regex::new(r"^[a-zA-Z_]\w*\.[a-zA-Z_]\w*$").unwrap().is_match(s) => Self::InterfacePort,
             _ => Self::Unknown
         }
     }
@@ -541,6 +796,7 @@ pub enum VerilogValue {
     Wire(Arc<VerilogWire>, Range<usize>),
     UndefinedWire(String),
     Number { width: u8, value: u128 }, // Max value is 2^128 -1
+    Interface(String),                 // interface instance
     NONE,
 }
 
@@ -563,6 +819,7 @@ impl VerilogValue {
         match self {
             Self::UndefinedWire(s) => s,
             Self::Wire(w, _) => &w.name,
+            Self::Interface(s) => s,
             _ => "",
         }
     }
@@ -570,13 +827,13 @@ impl VerilogValue {
     pub fn to_string(&self) -> String {
         match self {
             Wire(wire, range) => {
-                if range.end == 1 {
+                if range.end == 1 || wire.is_array() {
                     format!("{}", wire)
                 } else {
                     format!("{}[{}:{}]", wire, range.end - 1, range.start)
                 }
             }
-            VerilogValue::UndefinedWire(s) => {
+            VerilogValue::UndefinedWire(s) | VerilogValue::Interface(s) => {
                 format!("{}", s)
             }
             VerilogValue::Number { width, value } => {
@@ -593,7 +850,7 @@ mod test {
 
     #[test]
     fn test_port() {
-        simple_logger::init_with_level(log::Level::Info).unwrap();
+        let _ = simple_logger::init_with_level(log::Level::Info);
         let mut port1 = VerilogPort::new(PortDir::InPort, "port1", 6.into());
         port1.connect_undefined_signal("wire1", false);
         let mut port2 = VerilogPort::new(PortDir::OutPort, "port2", 6.into());
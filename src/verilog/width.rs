@@ -1,12 +1,17 @@
 use std::fmt::{Display, Formatter};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, Sub};
+use crate::config::Dialect;
 use crate::utils::calculator::StrCalc;
 use crate::verilog::parameter::Param;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Width {
     RawWidth(usize),
     LiteralWidth(String, usize),
+    /// packed dimensions from outer to inner, `[N-1:0][7:0]` => [N, 8]
+    Packed(Vec<Width>),
 }
 
 impl Default for Width {
@@ -38,6 +43,10 @@ impl Display for Width {
         match self {
             Width::RawWidth(x) => {write!(f, "{}", x)}
             Width::LiteralWidth(x, _) => {write!(f, "{}", x)}
+            Width::Packed(dims) => {
+                let s = dims.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+                write!(f, "{}", s.join("x"))
+            }
         }
     }
 }
@@ -47,13 +56,14 @@ impl Width {
         match self {
             Width::RawWidth(x) => {Width::RawWidth(*x)}
             Width::LiteralWidth(s, _) => {
-                let mut temp = s.clone();
-                for p in param {
-                    temp = temp.replace(
-                        &p.name,
-                        &format!("{}", p.get_value()),
-                    )
-                }
+                // whole names are replaced, `N` is not a part of `NUM`
+                let name_re = Regex::new(r"\b[a-zA-Z_]\w*\b").unwrap();
+                let temp = name_re.replace_all(s, |c: &Captures| {
+                    match param.iter().rev().find(|p| p.name == c[0] && !p.is_type()) {
+                        Some(p) => p.get_value().to_string(),
+                        None => c[0].to_string(),
+                    }
+                }).to_string();
                 let res = temp.calculate();
                 let t = if res.is_ok() {
                     res.unwrap()
@@ -63,20 +73,106 @@ impl Width {
                 };
                 Width::LiteralWidth(s.clone(), t)
             }
+            Width::Packed(dims) => Width::Packed(dims.iter().map(|d| d.width_from(param)).collect()),
+        }
+    }
+
+    ///
+    /// packed width from dimensions, one dimension is a plain width
+    ///
+    pub fn packed(dims: Vec<usize>) -> Self {
+        match dims.as_slice() {
+            [x] => Width::RawWidth(*x),
+            _ => Width::Packed(dims.into_iter().map(Width::RawWidth).collect()),
         }
     }
 
     pub fn width(&self) -> usize {
         match self {
             Width::RawWidth(x) => *x,
-            Width::LiteralWidth(_, v) => *v
+            Width::LiteralWidth(_, v) => *v,
+            Width::Packed(dims) => dims.iter().map(|d| d.width()).product(),
+        }
+    }
+
+    ///
+    /// width of every packed dimension, from outer to inner
+    ///
+    pub fn dims(&self) -> Vec<usize> {
+        match self {
+            Width::Packed(dims) => dims.iter().map(|d| d.width()).collect(),
+            _ => vec![self.width()],
         }
     }
 
+    pub fn is_packed(&self) -> bool {
+        matches!(self, Width::Packed(dims) if dims.len() > 1)
+    }
+
     pub fn is_literal(&self) -> bool {
         match self {
             Width::RawWidth(_) => {false}
             Width::LiteralWidth(_, _) => {true}
+            Width::Packed(dims) => dims.iter().any(|d| d.is_literal()),
+        }
+    }
+
+    ///
+    /// range in declaration, such as `[7   :0]` or `[3:0][7:0]`
+    /// blank for 1 bit, packed dimensions are flattened for verilog
+    ///
+    pub fn range_string(&self, dialect: Dialect) -> String {
+        if self.is_packed() && dialect == Dialect::SystemVerilog {
+            let s = self.dims().iter().map(|d| format!("[{}:0]", d.saturating_sub(1))).collect::<String>();
+            format!("{:<8}", s)
+        } else if self.width() < 2 {
+            " ".repeat(8)
+        } else {
+            format!("[{:<4}:0]", self.width()-1)
+        }
+    }
+
+    ///
+    /// range in declaration of typed net, the innermost dimension is the type itself
+    ///
+    pub fn outer_range_string(&self) -> String {
+        let dims = self.dims();
+        if dims.len() < 2 {
+            return String::new();
+        }
+        dims[..dims.len() - 1].iter().map(|d| format!("[{}:0]", d.saturating_sub(1))).collect()
+    }
+
+    ///
+    /// unpacked range in declaration, `[4][2]` => `[0:3][0:1]`
+    ///
+    pub fn unpacked_string(dims: &[usize]) -> String {
+        dims.iter().map(|d| format!("[0:{}]", d.saturating_sub(1))).collect()
+    }
+
+    ///
+    /// names of all the elements of unpacked array, `x` with [2][2] => `x[0][0]`, `x[0][1]` ...
+    ///
+    pub fn element_names(name: &str, dims: &[usize]) -> Vec<String> {
+        dims.iter().fold(vec![name.to_string()], |acc, d| {
+            acc.iter()
+                .flat_map(|n| (0..*d).map(move |i| format!("{}[{}]", n, i)))
+                .collect()
+        })
+    }
+
+    ///
+    /// packed dimensions are merged into one
+    ///
+    fn flatten(self) -> Self {
+        match self {
+            Width::Packed(dims) if dims.iter().any(|d| d.is_literal()) => {
+                let width = dims.iter().map(|d| d.width()).product();
+                let s = dims.iter().map(|d| format!("({})", d)).collect::<Vec<_>>();
+                Width::LiteralWidth(s.join(" * "), width)
+            }
+            Width::Packed(dims) => Width::RawWidth(dims.iter().map(|d| d.width()).product()),
+            w => w,
         }
     }
 }
@@ -86,11 +182,12 @@ impl Add for Width {
 
     fn add(self, rhs: Self) -> Self::Output {
         use Width::*;
-        match (self,rhs) {
+        match (self.flatten(), rhs.flatten()) {
             (RawWidth(x), RawWidth(y)) => RawWidth(x + y),
             (LiteralWidth(x, _), RawWidth(y)) => LiteralWidth(format!("{} + {}", x, y), 0),
             (RawWidth(x), LiteralWidth(y, _)) => LiteralWidth(format!("{} + {}", x, y), 0),
             (LiteralWidth(x, _), LiteralWidth(y, _)) => LiteralWidth(format!("{} + {}", x, y), 0),
+            _ => unreachable!(),
         }
     }
 }
@@ -100,11 +197,12 @@ impl Sub for Width {
 
     fn sub(self, rhs: Self) -> Self::Output {
         use Width::*;
-        match (self,rhs) {
+        match (self.flatten(), rhs.flatten()) {
             (RawWidth(x), RawWidth(y)) => RawWidth(x - y),
             (LiteralWidth(x, _), RawWidth(y)) => LiteralWidth(format!("{} - {}", x, y), 0),
-            (RawWidth(x), LiteralWidth(y, _)) => LiteralWidth(format!("{} - {}", x, y), 0),
-            (LiteralWidth(x, _), LiteralWidth(y, _)) => LiteralWidth(format!("{} - {}", x, y), 0),
+            (RawWidth(x), LiteralWidth(y, _)) => LiteralWidth(format!("{} - ({})", x, y), 0),
+            (LiteralWidth(x, _), LiteralWidth(y, _)) => LiteralWidth(format!("{} - ({})", x, y), 0),
+            _ => unreachable!(),
         }
     }
 }
@@ -113,9 +211,10 @@ impl Add<usize> for Width {
     type Output = Self;
     fn add(self, rhs: usize) -> Self::Output {
         use Width::*;
-        match self {
+        match self.flatten() {
             RawWidth(x) => RawWidth(x + rhs),
             LiteralWidth(x, _) => LiteralWidth(format!("{} + {}", x, rhs), 0),
+            Packed(_) => unreachable!(),
         }
     }
 }
@@ -124,9 +223,10 @@ impl Sub<usize> for Width {
     type Output = Self;
     fn sub(self, rhs: usize) -> Self::Output {
         use Width::*;
-        match self {
+        match self.flatten() {
             RawWidth(x) => RawWidth(x - rhs),
             LiteralWidth(x, _) => LiteralWidth(format!("{} - {}", x, rhs), 0),
+            Packed(_) => unreachable!(),
         }
     }
 }
@@ -135,9 +235,10 @@ impl Add<Width> for usize {
     type Output = Width;
     fn add(self, rhs: Width) -> Self::Output {
         use Width::*;
-        match rhs {
+        match rhs.flatten() {
             RawWidth(x) => RawWidth(x + self),
             LiteralWidth(x, _) => LiteralWidth(format!("{} + {}", x, self), 0),
+            Packed(_) => unreachable!(),
         }
     }
 }
@@ -146,9 +247,10 @@ impl Sub<Width> for usize {
     type Output = Width;
     fn sub(self, rhs: Width) -> Self::Output {
         use Width::*;
-        match rhs {
+        match rhs.flatten() {
             RawWidth(x) => RawWidth(x - self),
             LiteralWidth(x, _) => LiteralWidth(format!("{} - {}", x, self), 0),
+            Packed(_) => unreachable!(),
         }
     }
 }
\ No newline at end of file
@@ -1,31 +1,47 @@
 use std::borrow::Borrow;
 use std::cmp::max;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::Range;
 use std::sync::{Arc, LazyLock, Mutex};
 use colored::Colorize;
+use crate::verilog::diagnostic::{bit_ranges, Diagnostic, DiagnosticCollector, DiagnosticKind, Endpoint};
 use crate::verilog::port::{PortDir, VerilogPort, VerilogValue};
+use crate::config::Dialect;
+use crate::verilog::width::Width;
 
 pub struct WireBuilder {
     wires: BTreeMap<String, (Arc<VerilogWire>, WirePayload, WireError)>,
+    // packed dimensions of wires, from outer to inner
+    dims: HashMap<String, Vec<usize>>,
+    // unpacked dimensions of array, elements are registered as `name[i]`
+    arrays: HashMap<String, Vec<usize>>,
+    // user defined type of wires, such as `my_pkg::req_t`
+    types: HashMap<String, String>,
+    // findings of health check are only collected, not logged
+    quiet: bool,
 }
 static WIRE_BUILDER_INSTANCE: LazyLock<Mutex<WireBuilder>> = LazyLock::new(|| {
     Mutex::new(WireBuilder {
         wires: BTreeMap::new(),
+        dims: HashMap::new(),
+        arrays: HashMap::new(),
+        types: HashMap::new(),
+        quiet: false,
     })
 });
 impl WireBuilder {
     ///
     /// register wire which connected to output port
     ///
-    pub fn add_driver_wire(name: &str, range: &Range<usize>, is_inout: bool) -> Arc<VerilogWire> {
+    pub fn add_driver_wire(name: &str, range: &Range<usize>, is_inout: bool, from: Endpoint) -> Arc<VerilogWire> {
         let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
         let (arc_wire, payload, error) = wire_builder
             .wires
             .entry(name.into())
             .or_insert_with(|| (Arc::new(VerilogWire::new(name.into(), is_inout)), WirePayload::default(), WireError::default()));
+        payload.driver_from.push(from);
         for i in range.clone().into_iter() {
             if !payload.driver.insert(i) {
                 // dont report error in anytime, only in health_check()
@@ -39,12 +55,13 @@ impl WireBuilder {
     ///
     /// register wire which connected to input port
     ///
-    pub fn add_load_wire(name: &str, range: &Range<usize>, is_inout: bool) -> Arc<VerilogWire> {
+    pub fn add_load_wire(name: &str, range: &Range<usize>, is_inout: bool, from: Endpoint) -> Arc<VerilogWire> {
         let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
         let (arc_wire, payload, _error) = wire_builder
             .wires
             .entry(name.into())
             .or_insert_with(|| (Arc::new(VerilogWire::new(name.into(), is_inout)), WirePayload::default(), WireError::default()));
+        payload.load_from.push(from);
         for i in range.clone().into_iter() {
             payload.load.insert(i);
         }
@@ -54,7 +71,7 @@ impl WireBuilder {
     ///
     /// register wire which connected to output port
     ///
-    pub fn add_driver_wire_asport(name: &str, range: &Range<usize>, is_inout: bool) -> Arc<VerilogWire> {
+    pub fn add_driver_wire_asport(name: &str, range: &Range<usize>, is_inout: bool, from: Endpoint) -> Arc<VerilogWire> {
         let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
         let (arc_wire, payload, error) = wire_builder.wires.entry(name.into()).or_insert_with(|| {
             (
@@ -63,6 +80,7 @@ impl WireBuilder {
                 WireError::default(),
             )
         });
+        payload.driver_from.push(from);
         for i in range.clone().into_iter() {
             if !payload.driver.insert(i) {
                 // dont report error in anytime, only in health_check()
@@ -76,7 +94,7 @@ impl WireBuilder {
     ///
     /// register wire which connected to input port
     ///
-    pub fn add_load_wire_asport(name: &str, range: &Range<usize>, is_inout: bool) -> Arc<VerilogWire> {
+    pub fn add_load_wire_asport(name: &str, range: &Range<usize>, is_inout: bool, from: Endpoint) -> Arc<VerilogWire> {
         let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
         let (arc_wire, payload, _error) = wire_builder.wires.entry(name.into()).or_insert_with(|| {
             (
@@ -85,6 +103,7 @@ impl WireBuilder {
                 WireError::default(),
             )
         });
+        payload.load_from.push(from);
         for i in range.clone().into_iter() {
             payload.load.insert(i);
         }
@@ -96,7 +115,7 @@ impl WireBuilder {
     ///
     fn get_width(name: &str) -> usize {
         let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
-        let (_wire, WirePayload { driver, load}, _error) = wire_builder
+        let (_wire, WirePayload { driver, load, .. }, _error) = wire_builder
             .wires
             .get(name)
             .expect(&format!("Wire {} has not been defined", name));
@@ -153,10 +172,15 @@ impl WireBuilder {
     ///
     /// check all the wires has driver & load
     /// must call this function after connected all the port
+    /// return the number of error bits (undriven & multi-driver)
     ///
-    pub fn check_health() {
-        log::info!("{}",">>> WireBuilder health check start <<<<".bright_green().bold());
+    pub fn check_health() -> usize {
         let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        let quiet = wire_builder.quiet;
+        if !quiet {
+            log::info!("{}",">>> WireBuilder health check start <<<<".bright_green().bold());
+        }
+        let mut error_cnt = 0;
         for (wire, payload, error) in wire_builder.wires.values() {
             if wire.inout_tag {
                 continue;
@@ -164,17 +188,78 @@ impl WireBuilder {
             // Self::check_driver_load(&payload.driver, &payload.load, &wire.name);
             let undriven = Self::check_undriven(&payload.driver, &payload.load);
             let unload = Self::check_unload(&payload.driver, &payload.load);
-            for bit in undriven {
-                log::error!("wire {}[{}] has load but no driver", wire.name.red().bold(), bit);
+            let mut multi_driver = error.multi_driver.iter().cloned().collect::<Vec<_>>();
+            multi_driver.sort();
+            error_cnt += undriven.len() + multi_driver.len();
+            for range in bit_ranges(&undriven) {
+                if !quiet {
+                    log::error!("wire {}{} has load but no driver", wire.name.red().bold(), Self::range_string(&range));
+                }
+                DiagnosticCollector::report(
+                    Diagnostic::new(
+                        DiagnosticKind::Undriven,
+                        &wire.name,
+                        format!("wire {}{} has load but no driver", wire.name, Self::range_string(&range)),
+                    )
+                    .with_range(&range)
+                    .with_sources(payload.load_from.clone()),
+                );
             }
-            for bit in unload {
-                log::warn!("wire {}[{}] has driver but no load", wire.name.yellow().bold(), bit);
+            for range in bit_ranges(&unload) {
+                if !quiet {
+                    log::warn!("wire {}{} has driver but no load", wire.name.yellow().bold(), Self::range_string(&range));
+                }
+                DiagnosticCollector::report(
+                    Diagnostic::new(
+                        DiagnosticKind::Unload,
+                        &wire.name,
+                        format!("wire {}{} has driver but no load", wire.name, Self::range_string(&range)),
+                    )
+                    .with_range(&range)
+                    .with_sources(payload.driver_from.clone()),
+                );
             }
-            for bit in error.multi_driver.iter() {
-                log::error!("wire {}[{}] has multi-driver", wire.name.red().bold(), bit)
+            for range in bit_ranges(&multi_driver) {
+                if !quiet {
+                    log::error!("wire {}{} has multi-driver", wire.name.red().bold(), Self::range_string(&range));
+                }
+                DiagnosticCollector::report(
+                    Diagnostic::new(
+                        DiagnosticKind::MultiDriver,
+                        &wire.name,
+                        format!("wire {}{} has multi-driver", wire.name, Self::range_string(&range)),
+                    )
+                    .with_range(&range)
+                    .with_sources(payload.driver_from.clone()),
+                );
             }
         }
-        log::info!("{}","<<< WireBuilder health check end  >>>>".bright_green().bold());
+        if !quiet {
+            log::info!("{}","<<< WireBuilder health check end  >>>>".bright_green().bold());
+        }
+        error_cnt
+    }
+
+    ///
+    /// only collect the findings of health check & port connection without logging them
+    /// it is kept by `clear`
+    ///
+    pub fn set_quiet(quiet: bool) {
+        let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        wire_builder.quiet = quiet;
+    }
+
+    pub fn is_quiet() -> bool {
+        let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        wire_builder.quiet
+    }
+
+    fn range_string(range: &Range<usize>) -> String {
+        if range.len() == 1 {
+            format!("[{}]", range.start)
+        } else {
+            format!("[{}:{}]", range.end - 1, range.start)
+        }
     }
 
     ///
@@ -199,7 +284,7 @@ impl WireBuilder {
                             if payload.driver.len() > 0 { return true }
                         }
                         PortDir::InOutPort => { return true }
-                        PortDir::Unknown => {}
+                        PortDir::InterfacePort | PortDir::Unknown => {}
                     }
                 }
             }
@@ -210,6 +295,7 @@ impl WireBuilder {
                 VerilogValue::Wire(w, _range) => judge(&w.name),
                 VerilogValue::UndefinedWire(s) => judge(s),
                 VerilogValue::Number { .. } => true,
+                VerilogValue::Interface(_) => true,
                 VerilogValue::NONE => judge(&port.name)
             });
         }
@@ -231,6 +317,66 @@ impl WireBuilder {
     pub fn clear() {
         let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
         wire_builder.wires = BTreeMap::new();
+        wire_builder.dims = HashMap::new();
+        wire_builder.arrays = HashMap::new();
+        wire_builder.types = HashMap::new();
+    }
+
+    ///
+    /// record unpacked dimensions of array, elements are declared as one array
+    ///
+    pub fn add_array(name: &str, dims: Vec<usize>) {
+        let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        if let Some(old) = wire_builder.arrays.get(name) {
+            if old != &dims {
+                log::warn!("array {} is declared as {:?} and {:?}, the first one is used", name, old, dims);
+            }
+            return;
+        }
+        wire_builder.arrays.insert(name.into(), dims);
+    }
+
+    pub fn array_of(name: &str) -> Option<Vec<usize>> {
+        let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        wire_builder.arrays.get(name).cloned()
+    }
+
+    ///
+    /// record packed dimensions of wire, used by declaration & slicing on outer dimension
+    ///
+    pub fn add_dims(name: &str, dims: Vec<usize>) {
+        let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        if let Some(old) = wire_builder.dims.get(name) {
+            if old != &dims {
+                log::warn!("wire {} is declared as {:?} and {:?}, the first one is used", name, old, dims);
+            }
+            return;
+        }
+        wire_builder.dims.insert(name.into(), dims);
+    }
+
+    pub fn dims_of(name: &str) -> Option<Vec<usize>> {
+        let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        wire_builder.dims.get(name).cloned()
+    }
+
+    ///
+    /// record user defined type of wire, the wire is declared with this type
+    ///
+    pub fn add_type(name: &str, data_type: &str) {
+        let mut wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        if let Some(old) = wire_builder.types.get(name) {
+            if old != data_type {
+                log::warn!("wire {} is declared as {} and {}, the first one is used", name, old, data_type);
+            }
+            return;
+        }
+        wire_builder.types.insert(name.into(), data_type.into());
+    }
+
+    pub fn type_of(name: &str) -> Option<String> {
+        let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        wire_builder.types.get(name).cloned()
     }
 
     ///
@@ -239,37 +385,104 @@ impl WireBuilder {
     pub fn traverse_unport_wires() -> Vec<WirePrinter> {
         let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
         let mut res = Vec::new();
+        // (array name, width of element)
+        let mut arrays: BTreeMap<&str, usize> = BTreeMap::new();
         for item in wire_builder.wires.values() {
             if item.0.need_declaration() {
                 let name = item.0.name.clone();
+                let base = name.split('[').next().unwrap_or(&name);
+                if name.contains('[') && wire_builder.arrays.contains_key(base) {
+                    let width = 1+max(
+                        item.1.driver.iter().max().unwrap_or(&0),
+                        item.1.load.iter().max().unwrap_or(&0)
+                    );
+                    let (base, _) = wire_builder.arrays.get_key_value(base).unwrap();
+                    let w = arrays.entry(base.as_str()).or_insert(0);
+                    *w = max(*w, width);
+                    continue;
+                }
                 // let width = WireBuilder::get_width(&name);
                 let width = 1+max(
                     item.1.driver.iter().max().unwrap_or(&0),
                     item.1.load.iter().max().unwrap_or(&0)
                 );
-                res.push(WirePrinter::new(name, width));
+                // dimensions are dropped if they dont match the connected bits
+                let dims = wire_builder.dims
+                    .get(&name)
+                    .filter(|d| d.iter().product::<usize>() == width)
+                    .cloned()
+                    .unwrap_or(vec![width]);
+                let data_type = wire_builder.types.get(&name).cloned();
+                res.push(WirePrinter::new(name, dims).with_type(data_type));
             }
         }
+        for (name, width) in arrays {
+            let dims = wire_builder.dims.get(name).cloned().unwrap_or(vec![width]);
+            res.push(WirePrinter::new(name.into(), dims)
+                .with_unpacked(wire_builder.arrays[name].clone())
+                .with_type(wire_builder.types.get(name).cloned()));
+        }
         res
     }
 
     pub fn traverse_unload_undriven() -> Vec<(PortDir, usize, String)> {
         let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
         let mut res = Vec::new();
+        // (width of element, no element is driven, no element is loaded, some bits are not connected)
+        let mut arrays: BTreeMap<&str, (usize, bool, bool, bool)> = BTreeMap::new();
         for (wire, payload, _) in wire_builder.wires.values() {
             let undriven = Self::check_undriven(&payload.driver, &payload.load);
             let unload = Self::check_unload(&payload.driver, &payload.load);
-            if undriven.len() > 0 {
+            // array is a port only if all the elements are undriven or unload
+            let base = wire.name.split('[').next().unwrap_or(&wire.name);
+            if wire.name.contains('[') && wire_builder.arrays.contains_key(base) {
+                let width = 1 + max(
+                    payload.driver.iter().max().unwrap_or(&0),
+                    payload.load.iter().max().unwrap_or(&0),
+                );
+                let a = arrays.entry(base).or_insert((0, true, true, false));
+                a.0 = max(a.0, width);
+                a.1 &= payload.driver.is_empty();
+                a.2 &= payload.load.is_empty();
+                a.3 |= !undriven.is_empty() || !unload.is_empty();
+                continue;
+            }
+            if !undriven.is_empty() {
                 res.push((PortDir::InPort, undriven.len(), wire.name.clone()));
                 continue;
             }
-            if unload.len() > 0 {
+            if !unload.is_empty() {
                 res.push((PortDir::OutPort, unload.len(), wire.name.clone()))
             }
         }
+        for (name, (width, no_driver, no_load, unconnected)) in arrays {
+            match (no_driver, no_load) {
+                (true, false) => res.push((PortDir::InPort, width, name.to_string())),
+                (false, true) => res.push((PortDir::OutPort, width, name.to_string())),
+                _ if unconnected => log::warn!("array {} is partly driven or loaded, it is not added to top ports", name),
+                _ => {}
+            }
+        }
 
         res
+    }
 
+    ///
+    /// arrays whose elements have load but no driver at all
+    ///
+    pub fn undriven_arrays() -> Vec<String> {
+        let wire_builder = WIRE_BUILDER_INSTANCE.lock().unwrap();
+        let mut res = wire_builder.arrays
+            .keys()
+            .filter(|name| {
+                let elements = Width::element_names(name, &wire_builder.arrays[*name]);
+                elements.iter().all(|e| wire_builder.wires.get(e).is_none_or(|w| w.1.driver.is_empty()))
+                    && elements.iter().any(|e| wire_builder.wires.get(e).is_some_and(|w| !w.1.load.is_empty()))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        res.sort();
+        res
     }
 }
 #[derive(Debug, Default)]
@@ -277,6 +490,7 @@ pub struct VerilogWire {
     pub(crate) name: String,
     port_tag: bool,
     inout_tag: bool,
+    array_tag: bool,
 }
 impl VerilogWire {
     fn new(name: String, inout_tag: bool) -> Self {
@@ -284,6 +498,7 @@ impl VerilogWire {
             name,
             port_tag: false,
             inout_tag,
+            array_tag: false,
         }
     }
 
@@ -292,9 +507,30 @@ impl VerilogWire {
             name,
             port_tag: true,
             inout_tag,
+            array_tag: false,
         }
     }
 
+    ///
+    /// wire which is not registered by WireBuilder
+    ///
+    pub fn detached(name: &str) -> Arc<Self> {
+        Arc::new(Self::new(name.into(), false))
+    }
+
+    ///
+    /// whole array, its elements are registered by WireBuilder
+    ///
+    pub fn array(name: &str) -> Arc<Self> {
+        let mut wire = Self::new(name.into(), false);
+        wire.array_tag = true;
+        Arc::new(wire)
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.array_tag
+    }
+
     pub fn need_declaration(&self) -> bool {
         !self.port_tag
     }
@@ -327,6 +563,8 @@ impl Hash for VerilogWire {
 struct WirePayload {
     driver: HashSet<usize>,
     load: HashSet<usize>,
+    driver_from: Vec<Endpoint>,
+    load_from: Vec<Endpoint>,
 }
 
 #[derive(Default, Debug)]
@@ -342,48 +580,66 @@ impl Borrow<str> for VerilogWire {
 
 pub struct WirePrinter {
     name: String,
-    width: usize
+    width: Width,
+    unpacked: Vec<usize>,
+    data_type: Option<String>,
 }
 
 impl WirePrinter {
     
-    pub fn new(name: String, width: usize) -> Self {
+    pub fn new(name: String, dims: Vec<usize>) -> Self {
         Self {
-            name, width
+            name, width: Width::packed(dims), unpacked: Vec::new(), data_type: None
         }
     }
-    pub fn to_string(&self) -> Vec<String> {
-        let width_str = if self.width < 2 {
-            " ".repeat(8)
-        } else { 
-            format!("[{:<4}:0]", self.width-1)
+
+    pub fn with_type(mut self, data_type: Option<String>) -> Self {
+        self.data_type = data_type;
+        self
+    }
+
+    pub fn with_unpacked(mut self, dims: Vec<usize>) -> Self {
+        self.unpacked = dims;
+        self
+    }
+    pub fn to_string(&self, dialect: Dialect) -> Vec<String> {
+        let (net_type, width_str) = match &self.data_type {
+            Some(t) => (t.as_str(), self.width.outer_range_string()),
+            None => (dialect.net_type(), self.width.range_string(dialect)),
+        };
+        let name = if self.unpacked.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} {}", self.name, Width::unpacked_string(&self.unpacked))
         };
         vec![format!(
-            "wire {} {:<20}",
+            "{} {} {:<20}",
+            net_type,
             width_str,
-            self.name
+            name
         )]
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::verilog::diagnostic::Endpoint;
     use crate::verilog::wire::WireBuilder;
 
     #[test]
     fn test_builder() {
-        simple_logger::init_with_level(log::Level::Info).unwrap();
-        WireBuilder::add_load_wire("testwire1", &(0..1), false);
-        WireBuilder::add_driver_wire("testwire1", &(0..1), false);
-        WireBuilder::add_driver_wire("testwire2", &(0..6), false);
-        WireBuilder::add_load_wire("testwire3", &(0..2), false);
+        let _ = simple_logger::init_with_level(log::Level::Info);
+        WireBuilder::add_load_wire("testwire1", &(0..1), false, Endpoint::new(None, "port1"));
+        WireBuilder::add_driver_wire("testwire1", &(0..1), false, Endpoint::new(None, "port2"));
+        WireBuilder::add_driver_wire("testwire2", &(0..6), false, Endpoint::new(None, "port3"));
+        WireBuilder::add_load_wire("testwire3", &(0..2), false, Endpoint::new(None, "port4"));
         WireBuilder::builder_show();
         println!("wire1 width is {}", WireBuilder::get_width("testwire1"));
         println!("wire2 width is {}", WireBuilder::get_width("testwire2"));
         println!("wire3 width is {}", WireBuilder::get_width("testwire3"));
         // println!("wire3 width is {}", WireBuilder::get_width("testwire333"));
 
-        WireBuilder::add_driver_wire("testwire2", &(0..1), false);
+        WireBuilder::add_driver_wire("testwire2", &(0..1), false, Endpoint::new(None, "port5"));
         WireBuilder::check_health();
     }
 }
@@ -0,0 +1,157 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use colored::Colorize;
+use crate::config::Config;
+use crate::error::Result;
+use crate::verilog::diagnostic::{Diagnostic, DiagnosticCollector};
+use crate::verilog::filelist::FileList;
+use crate::verilog::wire::WireBuilder;
+use crate::{excel_path_of, excel_writer_of, from_excel};
+
+///
+/// modify time of watched files, `None` if the file is missing
+///
+type Snapshot = HashMap<PathBuf, Option<SystemTime>>;
+
+///
+/// poll the verilog files found by `traverse_v` and the excel
+/// rtl changed   => update the excel
+/// excel changed => regenerate the verilog top
+/// only the change of health check findings is printed
+///
+pub struct Watcher {
+    top_dir: PathBuf,
+    config: Config,
+    filelist: Option<FileList>,
+    interval: Duration,
+
+    excel: PathBuf,
+    rtl_files: Vec<PathBuf>,
+    findings: BTreeSet<String>,
+}
+
+impl Watcher {
+    pub fn new(top_dir: &Path, config: &Config, filelist: Option<FileList>) -> Result<Self> {
+        Ok(Self {
+            top_dir: top_dir.to_path_buf(),
+            config: config.clone(),
+            filelist,
+            interval: Duration::from_millis(1000),
+            excel: excel_path_of(top_dir, config)?,
+            rtl_files: Vec::new(),
+            findings: BTreeSet::new(),
+        })
+    }
+
+    ///
+    /// set the poll interval, default is 1000ms
+    ///
+    pub fn with_interval(mut self, interval: Option<Duration>) -> Self {
+        if let Some(t) = interval {
+            self.interval = t;
+        }
+        self
+    }
+
+    ///
+    /// run `from_file` once, then watch forever
+    ///
+    pub fn run(mut self) -> Result<()> {
+        // findings are printed by `report_delta` instead of the full health check
+        WireBuilder::set_quiet(true);
+        self.update_excel()?;
+        self.generate_v()?;
+        log::info!("{}", format!("watching {} files & {}", self.rtl_files.len(), self.excel.display()).bright_blue());
+        let mut rtl_snapshot = Self::snapshot(&self.rtl_files);
+        let mut excel_snapshot = Self::snapshot(&[self.excel.clone()]);
+        loop {
+            std::thread::sleep(self.interval);
+            let rtl_changed = Self::snapshot(&self.rtl_files) != rtl_snapshot;
+            let excel_changed = Self::snapshot(&[self.excel.clone()]) != excel_snapshot;
+            let res = if rtl_changed {
+                log::info!("{}", "rtl changed, update excel".bright_blue());
+                self.update_excel()
+            } else if excel_changed {
+                log::info!("{}", "excel changed, regenerate verilog".bright_blue());
+                self.generate_v()
+            } else {
+                continue;
+            };
+            // keep watching, the file may be saved halfway
+            if let Err(e) = res {
+                log::error!("{}", e);
+            }
+            // files written by this run are not seen as changed
+            rtl_snapshot = Self::snapshot(&self.rtl_files);
+            excel_snapshot = Self::snapshot(&[self.excel.clone()]);
+        }
+    }
+
+    fn update_excel(&mut self) -> Result<()> {
+        let mut writer = excel_writer_of(&self.top_dir, &self.config, self.filelist.clone());
+        writer.traverse_v()?;
+        self.rtl_files = writer.files().clone();
+        writer.generate_or_update()?;
+        self.report_delta();
+        Ok(())
+    }
+
+    fn generate_v(&mut self) -> Result<()> {
+        from_excel(&self.top_dir, &self.config)?;
+        self.report_delta();
+        Ok(())
+    }
+
+    ///
+    /// print findings which are new or resolved since the last run
+    ///
+    fn report_delta(&mut self) {
+        let findings = Self::finding_set(&DiagnosticCollector::diagnostics());
+        let (added, resolved) = Self::delta(&self.findings, &findings);
+        for s in resolved.iter() {
+            log::info!("{} {}", "-".bright_green(), s.bright_green());
+        }
+        for s in added.iter() {
+            log::info!("{} {}", "+".bright_red(), s.bright_red());
+        }
+        if added.is_empty() && resolved.is_empty() {
+            log::info!("health check unchanged, {} findings", findings.len());
+        }
+        self.findings = findings;
+    }
+
+    fn finding_set(diagnostics: &[Diagnostic]) -> BTreeSet<String> {
+        diagnostics
+            .iter()
+            .map(|d| format!("[{}] {}: {}", d.severity, d.kind, d.message))
+            .collect()
+    }
+
+    fn delta(old: &BTreeSet<String>, new: &BTreeSet<String>) -> (Vec<String>, Vec<String>) {
+        let added = new.difference(old).cloned().collect();
+        let resolved = old.difference(new).cloned().collect();
+        (added, resolved)
+    }
+
+    fn snapshot(files: &[PathBuf]) -> Snapshot {
+        files
+            .iter()
+            .map(|f| (f.clone(), std::fs::metadata(f).and_then(|m| m.modified()).ok()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delta() {
+        let old: BTreeSet<String> = ["a".to_string(), "b".to_string()].into();
+        let new: BTreeSet<String> = ["b".to_string(), "c".to_string()].into();
+        let (added, resolved) = Watcher::delta(&old, &new);
+        assert_eq!(added, vec!["c".to_string()]);
+        assert_eq!(resolved, vec!["a".to_string()]);
+    }
+}
@@ -0,0 +1,5 @@
+module array_sink (
+    input  logic [7:0] d [4],
+    output logic [7:0] q
+);
+endmodule
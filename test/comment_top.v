@@ -0,0 +1,21 @@
+module comment_ansi (
+    // ---- clock & reset ----
+    input  wire        clk,     // system clock
+    input  wire        rst_n,   /* active low */
+    /*
+     * write data of
+     * the fifo
+     */
+    input  wire [31:0] wdata,
+
+    output wire        full     // fifo is full
+);
+endmodule
+
+module comment_nonansi (a, b, c);
+    // input a
+    input a;
+    input b; // input b
+
+    output c;
+endmodule
@@ -0,0 +1,20 @@
+module conn_sub (
+    input  [7:0] din,
+    input        en,
+    output [7:0] dout
+);
+endmodule
+
+module conn_top #(
+    parameter DW = 8
+) (
+    input  [15:0]   data_in,
+    input           en,
+    output [DW-1:0] data_out
+);
+    conn_sub u_sub (
+        .din    (data_in[DW+7:DW]),
+        .en     (en & data_in[0]),
+        .dout   (data_out[DW-1:0])
+    );
+endmodule
@@ -0,0 +1,9 @@
+module diff_leaf #(
+    parameter DEPTH     = 16,
+    parameter ADDR_W    = $clog2(DEPTH),
+    parameter STOP_BITS = 1
+) (
+    input  wire [ADDR_W-1:0] addr,
+    output wire [DEPTH-1:0]  flag
+);
+endmodule
@@ -0,0 +1,26 @@
+interface axi_if;
+    logic [31:0] addr;
+    logic        valid;
+    modport master (output addr, output valid);
+    modport slave  (input addr, input valid);
+endinterface
+
+module iface_sub (
+    input  logic    clk,
+    axi_if.master   m_axi,
+    axi_if.slave    s_axi
+);
+endmodule
+
+module iface_top (
+    input  logic    clk,
+    axi_if.slave    s_axi
+);
+    axi_if u_bus ();
+
+    iface_sub u_sub (
+        .clk   (clk),
+        .m_axi (u_bus),
+        .s_axi (s_axi)
+    );
+endmodule
@@ -0,0 +1,42 @@
+module imp_fifo #(
+    parameter DW = 8
+) (
+    input           clk,
+    input  [DW-1:0] wdata,
+    output [DW-1:0] rdata,
+    output          empty
+);
+endmodule
+
+module imp_sync (
+    input  clk,
+    input  din,
+    output dout
+);
+endmodule
+
+module import_top (
+    input         clk,
+    input  [15:0] data_in,
+    input         async_in,
+    output [15:0] data_out,
+    output        empty,
+    output        flag
+);
+
+    imp_fifo #(
+        .DW     (16)
+    ) u_fifo (
+        .clk    (clk),
+        .wdata  (data_in[15:0]),
+        .rdata  (data_out[15:0]),
+        .empty  (empty)
+    );
+
+    imp_sync u_sync (
+        .clk    (clk),
+        .din    (async_in),
+        .dout   (flag)
+    );
+
+endmodule
@@ -0,0 +1,39 @@
+module inst_sub #(
+    parameter WIDTH = 4,
+    parameter DEPTH = 2
+) (
+    input               clk,
+    input  [WIDTH-1:0]  din,
+    output [WIDTH-1:0]  dout,
+    output              valid
+);
+endmodule
+
+module inst_top #(
+    parameter DW = 8
+) (
+    input           clk,
+    input  [15:0]   data_in,
+    output [15:0]   data_out,
+    output [1:0]    valid
+);
+
+    inst_sub #(
+        .WIDTH  (DW),
+        .DEPTH  (4)
+    ) u_named (
+        .clk    (clk),
+        .din    (data_in[7:0]),
+        .dout   ({data_out[7:4], data_out[3:0]}),
+        .valid  (valid[0])
+    );
+
+    inst_sub #(DW, 3) u_ordered (clk, data_in[15:8], data_out[15:8], );
+
+    inst_sub u_wildcard (
+        .clk,
+        .valid  (valid[1]),
+        .*
+    );
+
+endmodule
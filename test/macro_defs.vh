@@ -0,0 +1 @@
+`define DATA_W 8
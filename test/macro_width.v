@@ -0,0 +1,5 @@
+module macro_width (
+    input  [`DATA_W-1:0]  din,
+    output [`UNDEF_W-1:0] dout
+);
+endmodule
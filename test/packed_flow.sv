@@ -0,0 +1,11 @@
+module packed_src (
+    output bus_pkg::req_t [1:0] reqs,
+    output logic [3:0][7:0]     data
+);
+endmodule
+
+module packed_dst (
+    input  bus_pkg::req_t [1:0] reqs,
+    input  logic [3:0][7:0]     data
+);
+endmodule
@@ -0,0 +1,12 @@
+module packed_top #(
+    parameter N         = 4,
+    parameter NUM_BANKS = 2
+) (
+    input  logic [N-1:0][7:0] data,
+    output logic [1:0][3:0]   flag,
+    input  logic [7:0]        cfg [4],
+    output wire  [31:0]       rd_data [0:NUM_BANKS-1],
+    input  logic [7:0]        win [1:4],
+    input  logic [7:0]        sel [2:NUM_BANKS+1]
+);
+endmodule
@@ -0,0 +1,15 @@
+module param_leaf #(
+    parameter  DEPTH  = 16,
+    parameter  ADDR_W = $clog2(DEPTH),
+    localparam LAST   = DEPTH - 1
+) (
+    input  wire [ADDR_W-1:0] addr,
+    output wire [LAST:0]     flag
+);
+endmodule
+
+module param_top;
+    wire [5:0]  addr;
+    wire [63:0] flag;
+    param_leaf #(.DEPTH(64)) u_leaf (.addr(addr), .flag(flag));
+endmodule
@@ -0,0 +1,24 @@
+module dut (
+    input  wire clk,
+    output wire done
+);
+endmodule
+
+// behavioral model for simulation only
+// generate_top: skip
+module dut_model (
+    input  wire clk,
+    output wire done
+);
+endmodule
+
+module dut_tb;  // generate_top: skip
+endmodule
+
+module dut_wrap (
+    input  wire clk,
+    output wire done
+);
+    // generate_top: skip
+    dut u_dut (.clk(clk), .done(done));
+endmodule
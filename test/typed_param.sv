@@ -0,0 +1,10 @@
+module typed_param #(
+    parameter string       INIT_FILE = "rom.hex",
+    parameter logic [31:0] RESET_VAL = 32'hDEAD_BEEF,
+    parameter real         RATIO     = 1.5,
+    parameter type         T         = logic [7:0]
+) (
+    input  T    din,
+    output wire dout
+);
+endmodule
@@ -0,0 +1,11 @@
+module typed_top
+    import bus_pkg::*;
+(
+    input  logic            clk,
+    input  bus_pkg::req_t   req,
+    input  data_t [3:0]     wdata,
+    output word_t           rdata,
+    output int              count
+);
+    typedef logic [31:0] word_t;
+endmodule
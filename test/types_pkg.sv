@@ -0,0 +1,16 @@
+package bus_pkg;
+    parameter ADDR_W = 32;
+
+    typedef enum logic [1:0] {IDLE, READ, WRITE} op_e;
+
+    typedef struct packed {
+        op_e               op;
+        logic [ADDR_W-1:0] addr;
+        logic [7:0]        len;
+    } req_t;
+
+    typedef union packed {
+        logic [15:0]      half;
+        logic [1:0][7:0]  bytes;
+    } data_t;
+endpackage